@@ -0,0 +1,90 @@
+//! Native interfaces to the game. When run in a terminal the game
+//! is drawn full-screen, otherwise it falls back to reading commands
+//! line-by-line from stdin.
+
+mod repl;
+mod tui;
+
+use star_trip::{Options, HIST};
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: star_trip [--seed N] [--difficulty D]
+                 [--systems N] [--sectors N] [--history N] [--plain]
+
+When run in a terminal the game is drawn full-screen. Otherwise,
+or with --plain, commands are read from stdin one per line and
+the resulting screen is printed to stdout. Enter HELP for a
+listing of available commands.
+
+The difficulty is one of CADET, LIEUTENANT (the default), CAPTAIN
+or ADMIRAL, or a custom value between 60 and 240. The galaxy has
+--systems systems on a side, each with --sectors sectors on a side
+(both between 5 and 20, and 10 by default). Full-screen, the last
+--history commands (16 by default) can be recalled with Up and Down,
+or searched with Ctrl-R.";
+
+fn main() -> ExitCode {
+    let mut seed = None;
+    let mut options = Options::default();
+    let mut plain = false;
+    let mut history = HIST;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" | "-s" => match args.next().and_then(|s| s.parse::<u64>().ok()) {
+                Some(s) => seed = Some(s),
+                None => {
+                    eprintln!("--seed requires a numeric argument\n\n{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--difficulty" | "-d" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(d) => options.difficulty = d,
+                None => {
+                    eprintln!("--difficulty requires a preset name or value\n\n{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--systems" | "--sectors" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(n) if arg == "--systems" => options.systems = n,
+                Some(n) => options.sectors = n,
+                None => {
+                    eprintln!("{arg} requires a numeric argument\n\n{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--history" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(n) => history = n,
+                None => {
+                    eprintln!("--history requires a numeric argument\n\n{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--plain" | "-p" => plain = true,
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => {
+                eprintln!("Unrecognised argument: {arg}\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let seed = seed.unwrap_or_else(rand::random);
+    let result = if plain || !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        repl::run(seed, options)
+    } else {
+        tui::run(seed, options, history)
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("star_trip: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
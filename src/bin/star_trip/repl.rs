@@ -0,0 +1,95 @@
+//! The line-oriented interface: commands are read from stdin one
+//! per line, and the resulting screen is written to stdout as
+//! UTF-8 text.
+
+use star_trip::{cp437_to_char, DisplayBytes, GameState, Options, Terminal, WIDTH};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Runs the game until it ends or stdin is exhausted
+pub fn run(seed: u64, options: Options) -> io::Result<()> {
+    let mut state = GameState::new_with_options(seed, options);
+
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut stdin = stdin.lock();
+    let mut stdout = io::stdout().lock();
+
+    let difficulty = to_utf8(&state.options().difficulty.display_bytes());
+    writeln!(
+        stdout,
+        "Star Trip {} (seed {}, {difficulty} difficulty)\n\nEnter the HELP command for a listing of available commands.\n",
+        env!("CARGO_PKG_VERSION"),
+        state.seed()
+    )?;
+
+    let mut line = Vec::new();
+    loop {
+        // Only prompt a human, so that piped output stays clean
+        if interactive {
+            write!(stdout, "COMMAND => ")?;
+            stdout.flush()?;
+        }
+
+        line.clear();
+        if stdin.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+
+        // A fresh terminal for each command, so that only what
+        // it printed is shown and nothing is repeated
+        let mut term = Terminal::new();
+        let status = state.process_command(&line, &mut term);
+        print_screen(&mut stdout, &term)?;
+
+        match status {
+            1 => {
+                writeln!(
+                    stdout,
+                    "Mission accomplished! You achieved a score of {} ({difficulty}).",
+                    state.score()
+                )?;
+                return Ok(());
+            }
+            2 => {
+                writeln!(
+                    stdout,
+                    "Your ship has been destroyed. Final log entry:\n{}\nYou achieved a score of {} ({difficulty}).",
+                    to_utf8(state.final_log()).trim(),
+                    state.score()
+                )?;
+                return Ok(());
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Writes the latest message in full as UTF-8 text, truncating
+/// long lines as the display does and dropping trailing blank space.
+/// Nothing is written if there was no message
+fn print_screen(out: &mut impl Write, term: &Terminal) -> io::Result<()> {
+    let rows: Vec<String> = term
+        .lines()
+        .map(|row| to_utf8(&row[..row.len().min(WIDTH)]).trim_end().to_string())
+        .collect();
+    let end = rows
+        .iter()
+        .rposition(|r| !r.is_empty())
+        .map_or(0, |i| i + 1);
+
+    if end == 0 {
+        return Ok(());
+    }
+
+    for row in &rows[..end] {
+        writeln!(out, "{row}")?;
+    }
+    writeln!(out)
+}
+
+/// Converts CP-437 encoded text to UTF-8, keeping line breaks
+fn to_utf8(text: &[u8]) -> String {
+    text.iter()
+        .map(|&c| if c == b'\n' { '\n' } else { cp437_to_char(c) })
+        .collect()
+}
@@ -0,0 +1,238 @@
+//! The full-screen interface: the display buffer is drawn in the
+//! terminal using ANSI escape sequences, and keys are handled as
+//! they are by the browser frontend.
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{
+        Attribute as Effect, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+    terminal::{self, ClearType},
+};
+use star_trip::{char_to_cp437, cp437_to_char, Attribute, Colour, Game, Options};
+use std::io::{self, Write};
+use std::panic;
+
+/// Puts the terminal into full-screen raw mode, restoring it when
+/// dropped (including on error) or on panic
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        // Release builds abort on panic without dropping anything,
+        // so the terminal is restored before the panic is reported
+        let report = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            report(info);
+        }));
+
+        terminal::enable_raw_mode()?;
+        execute!(
+            io::stdout(),
+            terminal::EnterAlternateScreen,
+            terminal::Clear(ClearType::All),
+            cursor::Hide
+        )?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leaves full-screen raw mode
+fn restore() {
+    let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Runs the game until it ends or the player presses Escape,
+/// remembering the given number of commands in the history
+pub fn run(seed: u64, options: Options, history: usize) -> io::Result<()> {
+    let _screen = Screen::enter()?;
+    let mut out = io::stdout();
+
+    let mut game = Game::with_options(seed, options);
+    game.set_history_length(history);
+
+    // Any key starts the game, as a click does in the browser
+    draw(&mut out, &mut game)?;
+    if !pause(&mut out, &mut game)? {
+        return Ok(());
+    }
+    game.intro();
+    draw(&mut out, &mut game)?;
+
+    loop {
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            Event::Resize(..) => {
+                redraw(&mut out, &mut game)?;
+                continue;
+            }
+            _ => continue,
+        };
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            match key.code {
+                KeyCode::Char('c' | 'd') => return Ok(()),
+                KeyCode::Char('w') => game.delete_word(),
+                KeyCode::Char('u') => game.kill_line(),
+                KeyCode::Char('r') => game.search(),
+                _ => continue,
+            }
+            draw(&mut out, &mut game)?;
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char(' ') if game.paging() => game.page_down(),
+            KeyCode::PageDown => game.page_down(),
+            KeyCode::PageUp => game.page_up(),
+            // Only characters the tileset can draw are entered
+            KeyCode::Char(c) => {
+                if let Some(c) = char_to_cp437(c) {
+                    game.input(c.into());
+                }
+            }
+            KeyCode::Left => game.left(),
+            KeyCode::Right => game.right(),
+            KeyCode::Up => game.up(),
+            KeyCode::Down => game.down(),
+            KeyCode::Backspace => game.backspace(),
+            KeyCode::Delete => game.delete(),
+            KeyCode::Home => game.home(),
+            KeyCode::End => game.end(),
+            KeyCode::Insert => game.toggle_insert(),
+            KeyCode::Tab => {
+                game.complete();
+            }
+            // Mistakes are explained without using up a turn
+            KeyCode::Enter if !game.replaying() && !game.validate() => (),
+            KeyCode::Enter => match game.enter() {
+                0 => (),
+                status => {
+                    if status == 1 {
+                        game.win();
+                    } else {
+                        game.lose();
+                    }
+                    draw(&mut out, &mut game)?;
+                    pause(&mut out, &mut game)?;
+                    return Ok(());
+                }
+            },
+            _ => (),
+        }
+        draw(&mut out, &mut game)?;
+    }
+}
+
+/// Waits for a key press, returning false if it was a request to quit
+fn pause(out: &mut impl Write, game: &mut Game) -> io::Result<bool> {
+    loop {
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                let quit = key.code == KeyCode::Esc
+                    || (key.modifiers.contains(KeyModifiers::CONTROL)
+                        && matches!(key.code, KeyCode::Char('c' | 'd')));
+                return Ok(!quit);
+            }
+            Event::Resize(..) => redraw(out, game)?,
+            _ => (),
+        }
+    }
+}
+
+/// Draws the tiles of the display that have changed, converting
+/// them from CP-437
+fn draw(out: &mut impl Write, game: &mut Game) -> io::Result<()> {
+    let width = game.width();
+    let cells = game.dirty_cells();
+    let term = game.terminal();
+    let (buffer, styles) = (term.buffer(), term.attributes());
+
+    // Print runs of neighbouring tiles drawn alike together
+    let mut cells = cells.into_iter().map(usize::from).peekable();
+    while let Some(start) = cells.next() {
+        let mut end = start + 1;
+        while cells
+            .next_if(|&i| i == end && i % width != 0 && styles[i] == styles[start])
+            .is_some()
+        {
+            end += 1;
+        }
+        let text: String = buffer[start..end]
+            .iter()
+            .map(|&c| cp437_to_char(c))
+            .collect();
+        let (row, col) = (start / width, start % width);
+        queue!(out, cursor::MoveTo(col as u16, row as u16))?;
+        set_style(out, styles[start])?;
+        queue!(out, Print(text))?;
+    }
+    set_style(out, Attribute::DEFAULT)?;
+    out.flush()
+}
+
+/// Draws the whole display again, as the terminal may have lost it
+fn redraw(out: &mut impl Write, game: &mut Game) -> io::Result<()> {
+    queue!(out, terminal::Clear(ClearType::All))?;
+    game.invalidate();
+    draw(out, game)
+}
+
+/// Switches to drawing tiles as described by the attribute, leaving
+/// the terminal's own colours for tiles drawn as usual
+fn set_style(out: &mut impl Write, style: Attribute) -> io::Result<()> {
+    queue!(out, SetAttribute(Effect::Reset))?;
+    if style == Attribute::DEFAULT {
+        return Ok(());
+    }
+    let foreground = if style.is_bold() {
+        style.foreground().bright()
+    } else {
+        style.foreground()
+    };
+    queue!(
+        out,
+        SetForegroundColor(colour(foreground)),
+        SetBackgroundColor(colour(style.background()))
+    )?;
+    if style.is_blink() {
+        queue!(out, SetAttribute(Effect::SlowBlink))?;
+    }
+    if style.is_inverse() {
+        queue!(out, SetAttribute(Effect::Reverse))?;
+    }
+    Ok(())
+}
+
+/// The nearest of the terminal's named colours
+fn colour(c: Colour) -> Color {
+    match c {
+        Colour::Black => Color::Black,
+        Colour::Blue => Color::DarkBlue,
+        Colour::Green => Color::DarkGreen,
+        Colour::Cyan => Color::DarkCyan,
+        Colour::Red => Color::DarkRed,
+        Colour::Magenta => Color::DarkMagenta,
+        Colour::Brown => Color::DarkYellow,
+        Colour::LightGrey => Color::Grey,
+        Colour::DarkGrey => Color::DarkGrey,
+        Colour::LightBlue => Color::Blue,
+        Colour::LightGreen => Color::Green,
+        Colour::LightCyan => Color::Cyan,
+        Colour::LightRed => Color::Red,
+        Colour::LightMagenta => Color::Magenta,
+        Colour::Yellow => Color::Yellow,
+        Colour::White => Color::White,
+    }
+}
@@ -1,15 +1,33 @@
-/// Number of systems on a side in the galaxy
+/// Default number of systems on a side in the galaxy
 pub const SYSTEMS: usize = 10;
 
-/// Number of sectors on a side in a system
+/// Default number of sectors on a side in a system
 pub const SECTORS: usize = 10;
 
-/// Game difficulty (higher is harder)
-pub const DIFFICULTY: u8 = 100;
+/// Smallest number of systems or sectors on a side
+pub const MIN_SIZE: usize = 5;
 
-/// Victory condition
+/// Largest number of systems or sectors on a side
+pub const MAX_SIZE: usize = 20;
+
+/// Most systems or sectors on a side shown at once in a chart
+pub const VIEW: usize = 10;
+
+/// Easiest game difficulty (see Difficulty)
+pub const MIN_DIFFICULTY: u8 = 60;
+
+/// Hardest game difficulty (see Difficulty)
+pub const MAX_DIFFICULTY: u8 = 240;
+
+/// Victory condition for a galaxy of the default size
 pub const MISSION: usize = 10;
 
+/// Highest crew morale, as a percentage
+pub const MORALE: u8 = 100;
+
+/// Crew morale below which orders may be refused
+pub const MUTINY: u8 = 25;
+
 /// Width of "terminal" display in tiles
 pub const WIDTH: usize = 50;
 
@@ -25,8 +43,14 @@ pub const NUM_TILES: usize = 16;
 /// Time between drawing a tile (for dramatic effect)
 pub const DELAY_TIME: usize = 20;
 
+/// Rows of "terminal" above the command line
+pub const ROWS: usize = HEIGHT - 1;
+
+/// Rows of a long message shown at once, leaving room to say there is more
+pub const PAGE: usize = ROWS - 1;
+
 /// Extent of "terminal" excluding command line
-pub const SCREEN: usize = WIDTH * (HEIGHT - 1);
+pub const SCREEN: usize = WIDTH * ROWS;
 
 /// Default command prompt
 pub const COMMAND: &[u8] = b"COMMAND => ";
@@ -34,5 +58,5 @@ pub const COMMAND: &[u8] = b"COMMAND => ";
 /// Width of command prompt
 pub const CWIDTH: usize = COMMAND.len();
 
-/// Number of lines of command history retained
+/// Number of lines of command history retained by default
 pub const HIST: usize = 16;
@@ -19,7 +19,7 @@ impl DisplayBytes for &[u8] {
     }
 }
 
-impl DisplayBytes for usize {
+impl DisplayBytes for u64 {
     /// Convert an integer to an ASCII string in decimal
     fn display_bytes(&self) -> Vec<u8> {
         let mut n = *self;
@@ -35,6 +35,13 @@ impl DisplayBytes for usize {
     }
 }
 
+impl DisplayBytes for usize {
+    /// Convert an integer to an ASCII string in decimal
+    fn display_bytes(&self) -> Vec<u8> {
+        (*self as u64).display_bytes()
+    }
+}
+
 impl DisplayBytes for u8 {
     /// Convert an integer to an ASCII string in decimal
     fn display_bytes(&self) -> Vec<u8> {
@@ -42,6 +49,13 @@ impl DisplayBytes for u8 {
     }
 }
 
+impl DisplayBytes for u16 {
+    /// Convert an integer to an ASCII string in decimal
+    fn display_bytes(&self) -> Vec<u8> {
+        (*self as usize).display_bytes()
+    }
+}
+
 impl DisplayBytes for f64 {
     /// Convert a float to an ASCII string in decimal (truncates
     /// to whole part). Assumes float value is within range of
@@ -50,3 +64,44 @@ impl DisplayBytes for f64 {
         (self.floor() as usize).display_bytes()
     }
 }
+
+/// Glyphs for the CP-437 control codes 0x01 to 0x1F
+const CP437_LOW: [char; 31] = [
+    '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', '►', '◄', '↕', '‼',
+    '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+/// Glyphs for the upper half of CP-437, 0x80 to 0xFF
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+    'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+    'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+    '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+    '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±',
+    '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Convert a CP-437 code (as used by the display buffer and tileset)
+/// to the equivalent Unicode character. Blank tiles become spaces
+pub fn cp437_to_char(c: u8) -> char {
+    match c {
+        0 => ' ',
+        0x01..=0x1F => CP437_LOW[c as usize - 1],
+        0x7F => '⌂',
+        0x80..=0xFF => CP437_HIGH[c as usize - 0x80],
+        _ => c as char,
+    }
+}
+
+/// Convert a typed Unicode character to the CP-437 code for the
+/// same glyph, if it is printable ASCII or in the upper half
+pub fn char_to_cp437(c: char) -> Option<u8> {
+    match c {
+        ' '..='~' => Some(c as u8),
+        _ => CP437_HIGH
+            .iter()
+            .position(|&glyph| glyph == c)
+            .map(|i| 0x80 + i as u8),
+    }
+}
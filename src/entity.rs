@@ -1,35 +1,189 @@
-use crate::{constants::*, DisplayBytes};
-use rand::{
-    distributions::{Distribution, Standard},
-    thread_rng, Rng,
-};
+use crate::{Difficulty, DisplayBytes};
+use rand::{distributions::Distribution, Rng};
+use std::ops::Range;
 
 /// Status of a spaceship
 #[derive(Clone, Copy, PartialEq)]
 pub struct Ship {
-    pub energy: u8,
-    pub shields: u8,
-    pub torpedoes: u8,
-    pub range: u8,
+    pub energy: u16,
+    pub shields: u16,
+    pub torpedoes: u16,
+    pub range: u16,
+    /// The most sectors the ship can cover in a turn
+    pub speed: u16,
+    /// The most the ship can hold of each supply
+    pub capacity: Capacity,
+    /// The weapon the ship's shields have been tuned against, if any
+    pub tuned: Option<Weapon>,
+}
+
+/// The weapons a ship can be hit with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weapon {
+    Lasers,
+    Torpedoes,
+}
+
+impl DisplayBytes for Weapon {
+    fn display_bytes(&self) -> Vec<u8> {
+        match self {
+            Weapon::Lasers => b"lasers".to_vec(),
+            Weapon::Torpedoes => b"torpedoes".to_vec(),
+        }
+    }
+}
+
+/// The most energy, shields and torpedoes a ship can hold
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capacity {
+    pub energy: u16,
+    pub shields: u16,
+    pub torpedoes: u16,
 }
 
 impl Ship {
-    /// Randomly generate an enemy ship
-    pub fn enemy() -> Self {
-        let mut rng = thread_rng();
+    /// The player's ship as it sets out, short of a full load of torpedoes
+    pub fn player() -> Self {
+        Self {
+            energy: 255,
+            shields: 255,
+            torpedoes: 5,
+            range: 7,
+            speed: 10,
+            capacity: Capacity {
+                energy: 255,
+                shields: 255,
+                torpedoes: 7,
+            },
+            tuned: None,
+        }
+    }
+
+    /// Randomly generate a Klargon ship: lightly shielded, but fast
+    /// and well stocked with torpedoes. Like every enemy it is
+    /// stronger at higher difficulty
+    pub fn klargons<R: Rng + ?Sized>(rng: &mut R, difficulty: u8) -> Self {
+        Self::roll(
+            rng,
+            20..difficulty,
+            10..(difficulty / 2),
+            2..(difficulty / 10),
+            2..(difficulty / 20),
+            2,
+        )
+    }
 
-        let energy = rng.gen_range(20..DIFFICULTY);
-        let shields = rng.gen_range(20..DIFFICULTY);
-        let torpedoes = rng.gen_range(1..(DIFFICULTY / 20));
-        let range = rng.gen_range(2..(DIFFICULTY / 20));
+    /// Randomly generate a Remulin ship, which keeps to short
+    /// range so as to strike from under its cloak
+    pub fn remulins<R: Rng + ?Sized>(rng: &mut R, difficulty: u8) -> Self {
+        Self::roll(
+            rng,
+            20..difficulty,
+            20..difficulty,
+            1..(difficulty / 20),
+            1..3,
+            1,
+        )
+    }
+
+    /// Randomly generate a Faringa ship: unarmed, but swift and
+    /// with room for all the energy it can steal. They set out with
+    /// enough energy that they aren't already heading home
+    pub fn faringa<R: Rng + ?Sized>(rng: &mut R, difficulty: u8) -> Self {
+        let energy = (difficulty / 2)..difficulty;
+        let mut ship = Self::roll(rng, energy, 20..difficulty, 0..1, 2..3, 3);
+        ship.capacity.energy = 2 * u16::from(difficulty);
+        ship
+    }
+
+    /// Randomly generate a Berg ship, heavily shielded but slow
+    pub fn berg<R: Rng + ?Sized>(rng: &mut R, difficulty: u8) -> Self {
+        Self::roll(
+            rng,
+            20..difficulty,
+            (difficulty / 2)..difficulty,
+            1..(difficulty / 20),
+            2..(difficulty / 20),
+            1,
+        )
+    }
+
+    /// Randomly generate a fully supplied ship with stats in the
+    /// given ranges, and the given speed
+    fn roll<R: Rng + ?Sized>(
+        rng: &mut R,
+        energy: Range<u8>,
+        shields: Range<u8>,
+        torpedoes: Range<u8>,
+        range: Range<u8>,
+        speed: u16,
+    ) -> Self {
+        let energy = rng.gen_range(energy).into();
+        let shields = rng.gen_range(shields).into();
+        let torpedoes = rng.gen_range(torpedoes).into();
+        let range = rng.gen_range(range).into();
 
         Self {
             energy,
             shields,
             torpedoes,
             range,
+            speed,
+            capacity: Capacity {
+                energy,
+                shields,
+                torpedoes,
+            },
+            tuned: None,
         }
     }
+
+    /// Whether the supplies are within the ship's capacity
+    pub fn is_valid(&self) -> bool {
+        self.energy <= self.capacity.energy
+            && self.shields <= self.capacity.shields
+            && self.torpedoes <= self.capacity.torpedoes
+    }
+
+    /// Takes a hit of the given strength, on the shields first and
+    /// then on the energy, returning the damage done
+    pub fn absorb(&mut self, beam: u16) -> u16 {
+        let damage = beam.min(self.energy.saturating_add(self.shields));
+        let rem = damage.saturating_sub(self.shields);
+        self.shields = self.shields.saturating_sub(damage);
+        self.energy = self.energy.saturating_sub(rem);
+        damage
+    }
+
+    /// Adds energy, up to capacity, returning how much was added
+    pub fn charge(&mut self, energy: u16) -> u16 {
+        let added = energy.min(self.capacity.energy.saturating_sub(self.energy));
+        self.energy += added;
+        added
+    }
+
+    /// Uses up energy, down to none
+    pub fn drain(&mut self, energy: u16) {
+        self.energy = self.energy.saturating_sub(energy);
+    }
+
+    /// Diverts energy to the shields, as much as is available and
+    /// they can hold, returning how much was diverted
+    pub fn divert(&mut self, energy: u16) -> u16 {
+        let diverted = energy
+            .min(self.energy)
+            .min(self.capacity.shields.saturating_sub(self.shields));
+        self.energy -= diverted;
+        self.shields += diverted;
+        diverted
+    }
+
+    /// Restores every supply to capacity
+    pub fn refit(&mut self) {
+        self.energy = self.capacity.energy;
+        self.shields = self.capacity.shields;
+        self.torpedoes = self.capacity.torpedoes;
+    }
 }
 
 /// Possible entities that might be encountered in
@@ -47,6 +201,15 @@ pub enum Entity {
 }
 
 impl Entity {
+    /// Returns the ship of an enemy entity
+    pub fn ship(&self) -> Option<Ship> {
+        use Entity::*;
+        match self {
+            Klargons(ship) | Remulins(ship) | Faringa(ship) | Berg(ship) => Some(*ship),
+            _ => None,
+        }
+    }
+
     /// Update the ship status of an enemy entity
     pub fn update(&self, ship: Ship) -> Option<Entity> {
         use Entity::*;
@@ -60,18 +223,19 @@ impl Entity {
     }
 }
 
-impl Distribution<Entity> for Standard {
+impl Distribution<Entity> for Difficulty {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Entity {
         use Entity::*;
+        let difficulty = self.value();
         match rng.gen_range(0..=7) {
             0 => BlackHole,
             1 => Star,
             2 => Planet,
             3 => Base,
-            4 => Klargons(Ship::enemy()),
-            5 => Remulins(Ship::enemy()),
-            6 => Faringa(Ship::enemy()),
-            _ => Berg(Ship::enemy()),
+            4 => Klargons(Ship::klargons(rng, difficulty)),
+            5 => Remulins(Ship::remulins(rng, difficulty)),
+            6 => Faringa(Ship::faringa(rng, difficulty)),
+            _ => Berg(Ship::berg(rng, difficulty)),
         }
     }
 }
@@ -0,0 +1,601 @@
+use crate::{bconcat, constants::*, DisplayBytes, GameState, Options, Replay, Terminal};
+use rand::{thread_rng, Rng};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// The game as seen by a frontend: the game state together with
+/// the display it is drawn on
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Game {
+    /// The display, a terminal-like screen of text
+    term: Terminal,
+    /// The internal game state
+    state: GameState,
+    /// A recorded game being played back
+    playback: Option<Replay>,
+}
+
+/// The public interface exposed in Javascript by wasm-bindgen
+/// (when the `wasm` feature is enabled). This is largely a re-export
+/// of the Terminal-like functionality used to display the game state.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Game {
+    /// Creates a new Game instance with a random seed
+    pub fn new() -> Self {
+        Self::new_with_seed(thread_rng().gen())
+    }
+
+    /// Creates a new Game instance from the given seed
+    pub fn new_with_seed(seed: u64) -> Self {
+        Self::with_options(seed, Options::default())
+    }
+
+    /// Creates a new Game instance from the given seed and options.
+    /// The difficulty is either the name of a preset (CADET, LIEUTENANT,
+    /// CAPTAIN or ADMIRAL) or a custom value, and an error naming it is
+    /// returned if it is neither. The galaxy has the given number of
+    /// systems on a side, each with the given number of sectors on a
+    /// side (both between 5 and 20)
+    pub fn new_with_options(
+        seed: u64,
+        difficulty: &str,
+        systems: usize,
+        sectors: usize,
+    ) -> Result<Game, String> {
+        let difficulty = difficulty.parse().map_err(|_| {
+            format!("Unknown difficulty '{difficulty}': expected a preset name or value")
+        })?;
+        let options = Options {
+            difficulty,
+            systems,
+            sectors,
+        };
+        Ok(Self::with_options(seed, options))
+    }
+
+    /// Returns the seed used to generate the galaxy
+    pub fn seed(&self) -> u64 {
+        self.state.seed()
+    }
+
+    /// Returns the screen width (in tiles)
+    pub fn width(&self) -> usize {
+        WIDTH
+    }
+
+    /// Returns the screen height (in tiles)
+    pub fn height(&self) -> usize {
+        HEIGHT
+    }
+
+    /// Returns the number of tiles on a side in the tilesheet
+    pub fn num_tiles(&self) -> usize {
+        NUM_TILES
+    }
+
+    /// Returns the number of pixels on a side in a tile
+    pub fn tile_size(&self) -> usize {
+        TILE_SIZE
+    }
+
+    /// Returns the delay time used for drawing the screen
+    pub fn delay_time(&self) -> usize {
+        DELAY_TIME
+    }
+
+    /// Displays the introduction
+    pub fn intro(&mut self) {
+        self.term.message(bconcat!(
+            b"Welcome, Captain, to your new command, the HMS Venture. \
+Your mission is to defend the galaxy from the threat of the Klargons, \
+Remulins, Faringa and Berg. Defeat ",
+            self.state.goal(),
+            b" enemies to win.
+
+Your spaceship is well equipped with shields, lasers and torpedoes. \
+It can traverse great distances at faster-than-light speeds!
+
+Remember to keep track of your supplies, especially the energy that \
+powers your ship's vital functions. Dock at a starbase or investigate \
+stars to resupply.
+
+You're in command of an excellent crew, make sure to take care of \
+their morale by investigating interesting planets on your journey. \
+A demoralised crew is slow to react and may even refuse your orders.
+
+Finally, watch out for astrophysical phenomena such as supernovae \
+and black holes!
+
+Enter the HELP command for a listing of available commands. Good luck!"
+        ));
+
+        self.term.update_console();
+    }
+
+    /// Displays the victory message
+    pub fn win(&mut self) {
+        let score = self.state.score();
+        let difficulty = centre(bconcat!(b"(", self.state.options().difficulty, b")"));
+        self.term.splash(bconcat!(
+            b"Well done, Captain, you've succeeded in making the galaxy a safer place.
+
+Your ship and crew have survived this difficult mission. We thank you for \
+your service.
+
+You achieved a score of:
+
+
+                         ",
+            score,
+            b"
+",
+            difficulty.as_slice(),
+            b"
+
+
+
+
+To play again, refresh this webpage."
+        ));
+    }
+
+    /// Displays the defeat message
+    pub fn lose(&mut self) {
+        let score = self.state.score();
+        let difficulty = centre(bconcat!(b"(", self.state.options().difficulty, b")"));
+        self.term.splash(bconcat!(
+            b"Unfortunately you have failed your mission of making the galaxy a safer \
+place.
+
+Your ship has been destroyed, and only a handful of crew members made it to \
+the escape pods in time. Your final log entry reads:
+
+==================================================
+",
+            self.state.final_log(),
+            b"
+==================================================
+
+You achieved a score of:
+
+
+                         ",
+            score,
+            b"
+",
+            difficulty.as_slice(),
+            b"
+
+To play again, refresh this webpage."
+        ));
+    }
+
+    /// Serialises the game in progress so that it can be resumed later
+    pub fn save(&self) -> Vec<u8> {
+        self.state.save()
+    }
+
+    /// Restores a saved game, returning whether it was successful,
+    /// and ending any playback. If the save cannot be restored, an
+    /// explanation is displayed and the current game is left untouched
+    pub fn load(&mut self, data: &[u8]) -> bool {
+        match GameState::load(data) {
+            Ok(state) => {
+                self.state = state;
+                if self.playback.take().is_some() {
+                    self.term.set_line(b"");
+                    self.prompt();
+                }
+                self.term.message(bconcat!(
+                    b"Welcome back, Captain.
+
+Your saved game has been restored. The date is ",
+                    self.state.date(),
+                    b".
+
+Enter the SCAN command to survey your surroundings, or HELP for a listing \
+of available commands."
+                ));
+                self.term.update_console();
+                true
+            }
+            Err(err) => {
+                self.term.message(bconcat!(
+                    b"Unable to restore your saved game:
+
+",
+                    err,
+                    b"
+
+Your current game continues. Enter the HELP command for a listing of \
+available commands."
+                ));
+                self.term.update_console();
+                false
+            }
+        }
+    }
+
+    /// Serialises the commands entered so far so that the
+    /// game can be played back later
+    pub fn export_replay(&self) -> Vec<u8> {
+        self.state.replay().map(Replay::export).unwrap_or_default()
+    }
+
+    /// Starts playing back a recorded game, returning whether it was
+    /// successful. During playback each Enter runs the next recorded
+    /// command, after which the game can be continued as normal
+    pub fn import_replay(&mut self, data: &[u8]) -> bool {
+        match Replay::import(data) {
+            Ok(replay) => {
+                self.state = GameState::new_with_options(replay.seed(), replay.options());
+                self.state.start_recording();
+                self.term.message(bconcat!(
+                    b"Replaying game with seed ",
+                    replay.seed(),
+                    b".
+
+Press Enter to run each of the ",
+                    replay.len(),
+                    b" recorded commands in turn. Once the recording is finished \
+you may continue the game yourself."
+                ));
+                self.playback = Some(replay);
+                self.cue();
+                self.prompt();
+                true
+            }
+            Err(err) => {
+                self.term.message(bconcat!(
+                    b"Unable to play back the recorded game:
+
+",
+                    err
+                ));
+                self.term.update_console();
+                false
+            }
+        }
+    }
+
+    /// Returns whether a recorded game is being played back
+    pub fn replaying(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Returns a pointer to the display buffer
+    pub fn screen(&self) -> *const u8 {
+        self.term.screen()
+    }
+
+    /// Returns a pointer to how each tile of the display is drawn, as
+    /// one 16-bit attribute per tile (see `Attribute` for the layout)
+    pub fn attributes(&self) -> *const u16 {
+        self.term.attributes().as_ptr().cast()
+    }
+
+    /// Returns a pointer to the console part of the display buffer
+    pub fn console(&self) -> *const u8 {
+        self.term.console()
+    }
+
+    /// Returns the positions in the display buffer of the tiles changed
+    /// since this was last called, which are assumed to then be drawn
+    pub fn dirty_cells(&mut self) -> Vec<u16> {
+        let cells = self.term.dirty_cells();
+        self.term.mark_drawn();
+        cells
+    }
+
+    /// Has the whole display drawn again, e.g. after it was cleared
+    pub fn invalidate(&mut self) {
+        self.term.invalidate();
+    }
+
+    /// Enters the given character at the cursor, inserting or
+    /// overwriting depending on the mode
+    pub fn input(&mut self, c: u16) {
+        self.term.input(c);
+        self.prompt();
+    }
+
+    /// Switches between inserting and overwriting input
+    pub fn toggle_insert(&mut self) {
+        self.term.toggle_insert();
+    }
+
+    /// Deletes the character before the cursor
+    pub fn backspace(&mut self) {
+        self.term.backspace();
+        self.prompt();
+    }
+
+    /// Deletes the character under the cursor
+    pub fn delete(&mut self) {
+        self.term.delete();
+        self.prompt();
+    }
+
+    /// Deletes the word before the cursor
+    pub fn delete_word(&mut self) {
+        self.term.delete_word();
+        self.prompt();
+    }
+
+    /// Deletes everything before the cursor
+    pub fn kill_line(&mut self) {
+        self.term.kill_line();
+        self.prompt();
+    }
+
+    /// Moves the cursor to the start of the command-line
+    pub fn home(&mut self) {
+        self.term.home();
+        self.prompt();
+    }
+
+    /// Moves the cursor to the end of the command-line input
+    pub fn end(&mut self) {
+        self.term.end();
+        self.prompt();
+    }
+
+    /// Moves the cursor position one tile to the left
+    pub fn left(&mut self) {
+        self.term.left();
+        self.prompt();
+    }
+
+    /// Moves the cursor position one tile to the right
+    pub fn right(&mut self) {
+        self.term.right();
+        self.prompt();
+    }
+
+    /// Moves forward through the command history
+    pub fn down(&mut self) {
+        self.term.down();
+        self.prompt();
+    }
+
+    /// Moves backward through the command history
+    pub fn up(&mut self) {
+        self.term.up();
+        self.prompt();
+    }
+
+    /// Searches back through the command history as the text to find
+    /// is typed, or if already searching, finds an older match.
+    /// Any other key finishes the search, keeping the match to edit
+    pub fn search(&mut self) {
+        self.term.search();
+    }
+
+    /// Changes the number of commands remembered in the history
+    pub fn set_history_length(&mut self, length: usize) {
+        self.term.set_history_length(length);
+        self.prompt();
+    }
+
+    /// Returns whether Space shows more of a long message rather
+    /// than being typed, as when nothing has been typed yet
+    pub fn paging(&self) -> bool {
+        self.term.more() && self.term.line().trim_ascii().is_empty()
+    }
+
+    /// Shows the next page of a long message
+    pub fn page_down(&mut self) {
+        self.term.page_down();
+    }
+
+    /// Shows the previous page of a long message
+    pub fn page_up(&mut self) {
+        self.term.page_up();
+    }
+
+    /// Completes the name of the command being entered at the cursor,
+    /// returning whether the possible completions were listed on
+    /// screen instead, as happens when there is more than one
+    pub fn complete(&mut self) -> bool {
+        self.term.accept();
+        let line = self.term.line();
+        let cursor = self.term.cursor();
+        let boundary = |c: &u8| c.is_ascii_whitespace() || matches!(c, b';' | b'=');
+        let start = line[..cursor]
+            .iter()
+            .rposition(boundary)
+            .map_or(0, |i| i + 1);
+        let end = line[cursor..]
+            .iter()
+            .position(boundary)
+            .map_or(line.len(), |i| cursor + i);
+
+        // Only the first word of a command is a command name
+        if !matches!(
+            line[..start].trim_ascii_end().last(),
+            None | Some(b';' | b'=')
+        ) {
+            return false;
+        }
+
+        let names = self.state.completions(&line[start..cursor]);
+        let common = names
+            .iter()
+            .skip(1)
+            .fold(names.first().map_or(0, Vec::len), |n, name| {
+                names[0]
+                    .iter()
+                    .zip(name)
+                    .take(n)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            });
+        let listed = match names.as_slice() {
+            [] => false,
+            [name] => {
+                self.term
+                    .splice(start..end, bconcat!(name.as_slice(), b" "));
+                false
+            }
+            [name, ..] if common > cursor - start => {
+                self.term.splice(start..end, &name[..common]);
+                false
+            }
+            _ => {
+                let mut msg = b"Possible commands:\n\n   ".to_vec();
+                let mut width = WIDTH;
+                for name in &names {
+                    if width + name.len() + 2 > WIDTH {
+                        msg.extend_from_slice(b"\n   ");
+                        width = 3;
+                    }
+                    msg.push(b' ');
+                    msg.extend_from_slice(name);
+                    width += name.len() + 1;
+                }
+                self.term.message(&msg);
+                true
+            }
+        };
+        self.prompt();
+        listed
+    }
+
+    /// Checks the command-line without running it, returning whether
+    /// the command is understood. If not, the problem is explained on
+    /// screen and the command-line is left as it is to be corrected
+    pub fn validate(&mut self) -> bool {
+        self.term.accept();
+        match self.state.parse(self.term.line()) {
+            Ok(_) => true,
+            Err(err) => {
+                let msg = err.explain(self.term.line());
+                self.term.message(&msg);
+                self.term.update_console();
+                false
+            }
+        }
+    }
+
+    /// Consumes a line of user commands and processes them.
+    /// During playback the next recorded command is used instead
+    pub fn enter(&mut self) -> u8 {
+        if let Some(replay) = &mut self.playback {
+            if let Some(command) = replay.peek() {
+                self.term.set_line(command);
+            }
+            replay.advance();
+        }
+
+        let command = self.term.enter();
+        let status = self.state.process_command(&command, &mut self.term);
+        self.cue();
+        self.prompt();
+        status
+    }
+
+    /// Hints at the arguments of the command being entered
+    fn prompt(&mut self) {
+        let hint = self.state.hint(self.term.line());
+        self.term.set_hint(&hint);
+    }
+
+    /// Shows the next recorded command on the command-line,
+    /// finishing playback once there are none left
+    fn cue(&mut self) {
+        if let Some(replay) = &self.playback {
+            match replay.peek() {
+                Some(command) => self.term.set_line(command),
+                None => self.playback = None,
+            }
+        }
+    }
+}
+
+/// Accessors for native frontends, which are not exposed in Javascript
+impl Game {
+    /// Creates a new Game instance from the given seed and options
+    pub fn with_options(seed: u64, options: Options) -> Self {
+        //console_error_panic_hook::set_once();
+        let mut state = GameState::new_with_options(seed, options);
+        state.start_recording();
+
+        let version = bconcat!(b"#   Version ", env!("CARGO_PKG_VERSION").as_bytes());
+        let seed = bconcat!(b"#   Seed ", seed);
+        let difficulty = bconcat!(b"#   Difficulty ", options.difficulty);
+        let options = state.options();
+        let galaxy = bconcat!(
+            b"#   Galaxy ",
+            options.systems,
+            b"x",
+            options.systems,
+            b" systems of ",
+            options.sectors,
+            b"x",
+            options.sectors,
+            b" sectors"
+        );
+
+        let mut term = Terminal::new();
+        term.splash(bconcat!(
+            b"##################################################
+#                                                #
+#                                                #
+#                                                #
+#       .dBBBBP dBBBBBBP dBBBBBb   dBBBBBb       #
+#       BP                    BB       dBP       #
+#       `BBBBb   dBP      dBP BB   dBBBBK        #
+#          dBP  dBP      dBP  BB  dBP  BB        #
+#     dBBBBP'  dBP      dBBBBBBB dBP  dB'        #
+#                                                #
+#               dBBBBBBP dBBBBBb    dBP dBBBBBb  #
+#                            dBP            dB'  #
+#                dBP     dBBBBK   dBP   dBBBP'   #
+#               dBP     dBP  BB  dBP   dBP       #
+#              dBP     dBP  dB' dBP   dBP        #
+#                                                #
+",
+            pad(version).as_slice(),
+            b"
+",
+            pad(seed).as_slice(),
+            b"
+",
+            pad(difficulty).as_slice(),
+            b"
+",
+            pad(galaxy).as_slice(),
+            b"
+#               +----------------+               #
+#               + Click to start |               #
+#               +----------------+               #
+#                                                #
+##################################################"
+        ));
+        Self {
+            term,
+            state,
+            playback: None,
+        }
+    }
+
+    /// Returns the display
+    pub fn terminal(&self) -> &Terminal {
+        &self.term
+    }
+}
+
+/// Centres a line of text on the screen
+fn centre(line: &[u8]) -> Vec<u8> {
+    let mut centred = vec![b' '; WIDTH.saturating_sub(line.len()) / 2];
+    centred.extend_from_slice(line);
+    centred
+}
+
+/// Pads a line of the title screen out to its right-hand border
+fn pad(line: &[u8]) -> Vec<u8> {
+    let mut line = line.to_vec();
+    line.resize(WIDTH - 1, b' ');
+    line.push(b'#');
+    line
+}
@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+
+/// The command lines entered so far, most recent first, holding
+/// at most a fixed number of them
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+    entries: VecDeque<Vec<u8>>,
+    capacity: usize,
+}
+
+impl History {
+    /// Creates an empty history holding at most `capacity` lines
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a line as the most recent, moving it to the front if
+    /// it was entered before. Blank lines are not recorded
+    pub fn push(&mut self, line: &[u8]) {
+        let line = line.trim_ascii();
+        if line.is_empty() || self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|entry| entry != line);
+        self.entries.truncate(self.capacity - 1);
+        self.entries.push_front(line.to_vec());
+    }
+
+    /// Returns the line entered `age` lines ago, where 0 is the most recent
+    pub fn get(&self, age: usize) -> Option<&[u8]> {
+        self.entries.get(age).map(Vec::as_slice)
+    }
+
+    /// Finds the most recent line containing the text, starting `from`
+    /// lines ago, and returns how long ago it was entered
+    pub fn search(&self, text: &[u8], from: usize) -> Option<usize> {
+        (from..self.entries.len()).find(|&age| {
+            text.is_empty() || self.entries[age].windows(text.len()).any(|w| w == text)
+        })
+    }
+
+    /// Changes the number of lines held, forgetting the oldest if need be
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.entries.truncate(capacity);
+    }
+
+    /// Returns the most lines held at once
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of lines held
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no lines are held
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the lines, most recent first
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.entries.iter().map(Vec::as_slice)
+    }
+}
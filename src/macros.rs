@@ -1,18 +1,3 @@
-/// A simple macro to expand an index,
-/// given first the sector co-ordinate (x, y) and
-/// second the system co-ordinate (X, Y)
-#[macro_export]
-macro_rules! index {
-    ($x:expr, $y:expr, $X:expr, $Y:expr) => {
-        $x + SECTORS * ($y + SECTORS * ($X + SYSTEMS * $Y))
-    };
-
-    ($p:ident) => {{
-        let (x, y, xx, yy) = $p;
-        x + SECTORS * (y + SECTORS * (xx + SYSTEMS * yy))
-    }};
-}
-
 /// Convert numbers to ASCII byte strings (decimal representation)
 /// and concatenate with provided byte strings.
 /// Requires the `DisplayByte` trait to be implemented
@@ -20,7 +5,9 @@ macro_rules! index {
 /// # Examples
 ///
 /// ```
-/// let b = bconcat!(b"I am ", 45, b" years old!");
+/// use star_trip::{bconcat, DisplayBytes};
+///
+/// let b = bconcat!(b"I am ", 45usize, b" years old!");
 /// assert_eq!(b, b"I am 45 years old!");
 /// ```
 #[macro_export]
@@ -32,22 +19,23 @@ macro_rules! bconcat {
     };
 }
 
-/// Determine if an entity is nearby
+/// Determine if an entity is in a sector adjacent to the player,
+/// and not yet visited, returning its sector and kind
 #[macro_export]
 macro_rules! nearby {
     ($state:ident, $($entity:tt),*) => {
         'nearby: {
-            let (i, j, xx, yy) = $state.position;
-            for (x, y) in adjacent(i, j) {
-                if $state.visited.contains(&(x, y, xx, yy)) {
+            for sector in $state.position.sector.adjacent(&$state.options) {
+                let position = $state.local(sector);
+                if $state.visited.contains(&position) {
                     continue;
                 }
 
-                match $state.galaxy[index!(x, y, xx, yy)] {
+                match $state.at(position) {
                     $(
                         Some(Entity::$entity) => {
-                            $state.visited.insert((x, y, xx, yy));
-                            break 'nearby Some((x, y, Entity::$entity));
+                            $state.visited.insert(position);
+                            break 'nearby Some((sector, Entity::$entity));
                         },
                     )*
                     _ => ()
@@ -0,0 +1,109 @@
+use crate::{bconcat, constants::*, DisplayBytes};
+use std::str::FromStr;
+
+/// How hard the game is, shaping the strength and number of enemies,
+/// the damage done by torpedoes and collisions, and the energy found
+/// by investigating
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Difficulty {
+    Cadet,
+    #[default]
+    Lieutenant,
+    Captain,
+    Admiral,
+    /// A custom value between MIN_DIFFICULTY and MAX_DIFFICULTY
+    Custom(u8),
+}
+
+impl Difficulty {
+    /// Returns the numeric difficulty (higher is harder)
+    pub fn value(&self) -> u8 {
+        use Difficulty::*;
+        match self {
+            Cadet => 60,
+            Lieutenant => 100,
+            Captain => 160,
+            Admiral => 220,
+            Custom(v) => (*v).clamp(MIN_DIFFICULTY, MAX_DIFFICULTY),
+        }
+    }
+
+    /// Returns the preset matching a numeric difficulty,
+    /// or a custom difficulty if there is none
+    pub fn from_value(value: u8) -> Self {
+        use Difficulty::*;
+        [Cadet, Lieutenant, Captain, Admiral]
+            .into_iter()
+            .find(|d| d.value() == value)
+            .unwrap_or(Custom(value.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY)))
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ();
+
+    /// Parses a preset name (case-insensitive) or a numeric difficulty
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Difficulty::*;
+        match s.to_ascii_lowercase().as_str() {
+            "cadet" => Ok(Cadet),
+            "lieutenant" => Ok(Lieutenant),
+            "captain" => Ok(Captain),
+            "admiral" => Ok(Admiral),
+            n => n.parse().map(Self::from_value).map_err(|_| ()),
+        }
+    }
+}
+
+impl DisplayBytes for Difficulty {
+    fn display_bytes(&self) -> Vec<u8> {
+        use Difficulty::*;
+        match self {
+            Cadet => b"CADET".to_vec(),
+            Lieutenant => b"LIEUTENANT".to_vec(),
+            Captain => b"CAPTAIN".to_vec(),
+            Admiral => b"ADMIRAL".to_vec(),
+            Custom(_) => bconcat!(b"CUSTOM (", self.value(), b")").to_vec(),
+        }
+    }
+}
+
+/// Settings chosen at the start of a game
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+    pub difficulty: Difficulty,
+    /// Number of systems on a side in the galaxy
+    pub systems: usize,
+    /// Number of sectors on a side in a system
+    pub sectors: usize,
+}
+
+impl Options {
+    /// Returns these options with the galaxy dimensions limited
+    /// to between MIN_SIZE and MAX_SIZE
+    pub fn clamped(self) -> Self {
+        Self {
+            systems: self.systems.clamp(MIN_SIZE, MAX_SIZE),
+            sectors: self.sectors.clamp(MIN_SIZE, MAX_SIZE),
+            ..self
+        }
+    }
+
+    /// Returns the number of enemies to defeat to win, which
+    /// scales with the number of systems in the galaxy
+    pub fn mission(&self) -> usize {
+        let systems = (self.systems * self.systems) as f64;
+        let mission = MISSION as f64 * systems / (SYSTEMS * SYSTEMS) as f64;
+        (mission.round() as usize).max(1)
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::default(),
+            systems: SYSTEMS,
+            sectors: SECTORS,
+        }
+    }
+}
@@ -0,0 +1,729 @@
+use crate::{bconcat, DisplayBytes};
+use std::ops::Range;
+
+/// A command given by the captain, with its arguments
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Help,
+    /// Move at the given speed towards sector (x, y),
+    /// optionally in system (X, Y)
+    Move {
+        speed: usize,
+        x: usize,
+        y: usize,
+        system: Option<(usize, usize)>,
+    },
+    Laser {
+        energy: usize,
+        x: usize,
+        y: usize,
+    },
+    Torpedo {
+        number: usize,
+        x: usize,
+        y: usize,
+    },
+    Shields {
+        energy: usize,
+    },
+    Scan,
+    Survey,
+    Investigate,
+    Dock,
+    /// Report the state of the ship's systems
+    Damage,
+    /// Show a page of the log, or the latest page
+    Log {
+        page: Option<usize>,
+    },
+    /// Define a macro running the body, or delete it if empty
+    Define {
+        name: Vec<u8>,
+        body: Vec<u8>,
+    },
+    Macros,
+    /// Let the name stand for a built-in command
+    Alias {
+        name: Vec<u8>,
+        command: &'static CommandSpec,
+    },
+    Unalias {
+        name: Vec<u8>,
+    },
+    Aliases,
+    Quit,
+}
+
+/// Description of a command, from which commands are
+/// parsed and help is given
+#[derive(Debug, PartialEq)]
+pub struct CommandSpec {
+    /// Name of the command
+    pub name: &'static [u8],
+    /// Short form of the name, if any
+    pub alias: &'static [u8],
+    /// Arguments, with optional ones in brackets
+    pub args: &'static [u8],
+    /// What the command does (hidden from HELP if empty)
+    pub help: &'static [u8],
+}
+
+/// All the commands understood by the game
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: b"HELP",
+        alias: b"H",
+        args: b"",
+        help: b"print this list of commands",
+    },
+    CommandSpec {
+        name: b"MOVE",
+        alias: b"M",
+        args: b"s x y [X Y]",
+        help: b"move towards sector position (x, y) [optionally in system (X, Y)] \
+            at speed s, where s is between 1 and 10",
+    },
+    CommandSpec {
+        name: b"LASER",
+        alias: b"L",
+        args: b"e x y",
+        help: b"fire lasers with energy e towards position (x, y)",
+    },
+    CommandSpec {
+        name: b"TORPEDO",
+        alias: b"T",
+        args: b"t x y",
+        help: b"fire t torpedoes towards position (x, y)",
+    },
+    CommandSpec {
+        name: b"SHIELDS",
+        alias: b"SH",
+        args: b"e",
+        help: b"raise shields using energy e",
+    },
+    CommandSpec {
+        name: b"SCAN",
+        alias: b"SC",
+        args: b"",
+        help: b"perform a short range scan of the system",
+    },
+    CommandSpec {
+        name: b"SURVEY",
+        alias: b"SU",
+        args: b"",
+        help: b"perform a long range scan of the galaxy",
+    },
+    CommandSpec {
+        name: b"INVESTIGATE",
+        alias: b"I",
+        args: b"",
+        help: b"search for energy and lift morale",
+    },
+    CommandSpec {
+        name: b"DOCK",
+        alias: b"D",
+        args: b"",
+        help: b"resupply at a base and rest the crew",
+    },
+    CommandSpec {
+        name: b"DAMAGE",
+        alias: b"DA",
+        args: b"",
+        help: b"report damage to the ship's systems",
+    },
+    CommandSpec {
+        name: b"LOG",
+        alias: b"",
+        args: b"[n]",
+        help: b"print page n of the ship's log",
+    },
+    CommandSpec {
+        name: b"DEFINE",
+        alias: b"",
+        args: b"name = c; ...",
+        help: b"define a macro running the commands c in turn (or delete it if none)",
+    },
+    CommandSpec {
+        name: b"MACROS",
+        alias: b"",
+        args: b"",
+        help: b"list the defined macros",
+    },
+    CommandSpec {
+        name: b"ALIAS",
+        alias: b"",
+        args: b"[name command]",
+        help: b"give a command another name, or list the aliases if none given",
+    },
+    CommandSpec {
+        name: b"UNALIAS",
+        alias: b"",
+        args: b"name",
+        help: b"remove an alias",
+    },
+    CommandSpec {
+        name: b"QUIT",
+        alias: b"Q",
+        args: b"",
+        help: b"",
+    },
+];
+
+impl CommandSpec {
+    /// Looks up a command by its name or alias, ignoring case
+    pub fn find(word: &[u8]) -> Option<&'static CommandSpec> {
+        COMMANDS.iter().find(|spec| {
+            word.eq_ignore_ascii_case(spec.name)
+                || (!spec.alias.is_empty() && word.eq_ignore_ascii_case(spec.alias))
+        })
+    }
+
+    /// The name and arguments, e.g. `MOVE s x y [X Y]`
+    pub fn usage(&self) -> Vec<u8> {
+        if self.args.is_empty() {
+            self.name.to_vec()
+        } else {
+            bconcat!(self.name, b" ", self.args).to_vec()
+        }
+    }
+
+    /// Names of the arguments, without brackets
+    fn arg_names(&self) -> impl Iterator<Item = &'static [u8]> {
+        words(self.args).map(|(word, _)| {
+            let start = word.iter().position(|&c| c != b'[').unwrap_or(word.len());
+            let end = word
+                .iter()
+                .rposition(|&c| c != b']')
+                .map_or(start, |i| i + 1);
+            &word[start..end.max(start)]
+        })
+    }
+}
+
+/// Reasons a command could not be understood
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// The first word is not a known command
+    Unknown(Vec<u8>),
+    /// An argument is not a whole number
+    NotANumber(Vec<u8>),
+    /// The first argument must be greater than zero
+    Zero,
+    /// The named argument is missing
+    Missing(&'static [u8]),
+    /// More arguments were given than the command takes
+    Unexpected,
+    /// The given text was expected
+    Expected(&'static [u8]),
+    /// A name that is not a single word of letters and digits
+    BadName(Vec<u8>),
+    /// A name already used by a built-in command
+    Reserved(Vec<u8>),
+    /// A name already used by a macro or alias
+    Taken(Vec<u8>),
+    /// A DEFINE within a macro
+    Nested,
+}
+
+/// A command that could not be understood, and why
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The position of the offending word in the line (in bytes)
+    pub span: Range<usize>,
+    /// The command being given, if it was recognised
+    pub spec: Option<&'static CommandSpec>,
+}
+
+impl ParseError {
+    /// Moves the error along the line, for errors found
+    /// within part of a line
+    fn offset(self, by: usize) -> Self {
+        Self {
+            span: (self.span.start + by)..(self.span.end + by),
+            ..self
+        }
+    }
+
+    /// Explains the error, quoting the line with the
+    /// offending word underlined
+    pub fn explain(&self, line: &[u8]) -> Vec<u8> {
+        let mut out = b"    ".to_vec();
+        out.extend(
+            line.iter()
+                .map(|&c| if c.is_ascii_whitespace() { b' ' } else { c }),
+        );
+        out.truncate(out.trim_ascii_end().len());
+        out.extend_from_slice(b"\n    ");
+        out.resize(out.len() + self.span.start, b' ');
+        out.resize(out.len() + self.span.len().max(1), b'^');
+        out.extend_from_slice(b"\n\n");
+        out.extend(self.display_bytes());
+        out
+    }
+}
+
+impl DisplayBytes for ParseError {
+    fn display_bytes(&self) -> Vec<u8> {
+        use ParseErrorKind::*;
+        let first = self
+            .spec
+            .and_then(|spec| spec.arg_names().next())
+            .unwrap_or(b"");
+        let msg = match &self.kind {
+            Unknown(word) => bconcat!(b"Unrecognised command '", word.as_slice(), b"'.").to_vec(),
+            NotANumber(word) => {
+                bconcat!(b"Expected a whole number, found '", word.as_slice(), b"'.").to_vec()
+            }
+            Zero => bconcat!(b"The argument ", first, b" must be greater than zero.").to_vec(),
+            Missing(name) => bconcat!(b"Missing the argument ", name, b".").to_vec(),
+            Unexpected => b"Too many arguments.".to_vec(),
+            Expected(text) => bconcat!(b"Expected '", text, b"'.").to_vec(),
+            BadName(name) => bconcat!(
+                b"Invalid name '",
+                name.as_slice(),
+                b"'. Names must start with a letter and contain only letters and digits."
+            )
+            .to_vec(),
+            Reserved(name) => bconcat!(b"'", name.as_slice(), b"' is already a command.").to_vec(),
+            Taken(name) => bconcat!(
+                b"'",
+                name.as_slice(),
+                b"' is already the name of a macro or alias."
+            )
+            .to_vec(),
+            Nested => b"Macros cannot define other macros.".to_vec(),
+        };
+
+        match self.spec {
+            Some(spec) => bconcat!(
+                msg.as_slice(),
+                b"\n\nUsage:\n\n    ",
+                spec.usage().as_slice(),
+                b"\n\nRun HELP for more commands."
+            )
+            .to_vec(),
+            None => bconcat!(
+                msg.as_slice(),
+                b"\n\nTry the HELP command for a list of possible commands!"
+            )
+            .to_vec(),
+        }
+    }
+}
+
+impl Command {
+    /// Parses a line of input, returning `None` if it is blank
+    pub fn parse(line: &[u8]) -> Result<Option<Command>, ParseError> {
+        Self::parse_with(line, &Aliases::default())
+    }
+
+    /// Parses a line of input, also accepting the given aliases
+    fn parse_with(line: &[u8], aliases: &Aliases) -> Result<Option<Command>, ParseError> {
+        use Command::*;
+
+        let mut words = words(line);
+        let Some((name, span)) = words.next() else {
+            return Ok(None);
+        };
+        let Some(spec) = CommandSpec::find(name).or_else(|| aliases.get(name)) else {
+            return Err(ParseError {
+                kind: ParseErrorKind::Unknown(name.to_vec()),
+                span,
+                spec: None,
+            });
+        };
+        let error = |kind, span| ParseError {
+            kind,
+            span,
+            spec: Some(spec),
+        };
+
+        if spec.name == b"DEFINE" {
+            return parse_define(line, span.end)
+                .map(Some)
+                .map_err(|(kind, span)| error(kind, span));
+        }
+        if spec.name == b"ALIAS" || spec.name == b"UNALIAS" {
+            let args: Vec<_> = words.collect();
+            let end = args.last().map_or(span.end, |(_, span)| span.end);
+            let command = match (spec.name, args.as_slice()) {
+                (b"ALIAS", []) => Aliases,
+                (b"ALIAS", [(name, span), (word, command)]) => {
+                    check_name(name, span.clone()).map_err(|(kind, span)| error(kind, span))?;
+                    let Some(command) = CommandSpec::find(word) else {
+                        return Err(error(
+                            ParseErrorKind::Unknown(word.to_vec()),
+                            command.clone(),
+                        ));
+                    };
+                    Alias {
+                        name: name.to_ascii_uppercase(),
+                        command,
+                    }
+                }
+                (b"ALIAS", [_]) => {
+                    return Err(error(
+                        ParseErrorKind::Missing(b"command"),
+                        (end + 1)..(end + 1),
+                    ))
+                }
+                (b"UNALIAS", [(name, _)]) => Unalias {
+                    name: name.to_ascii_uppercase(),
+                },
+                (b"UNALIAS", []) => {
+                    return Err(error(
+                        ParseErrorKind::Missing(b"name"),
+                        (end + 1)..(end + 1),
+                    ))
+                }
+                _ => {
+                    let extra = args
+                        .get(spec.arg_names().count())
+                        .map_or(span, |(_, span)| span.clone());
+                    return Err(error(ParseErrorKind::Unexpected, extra));
+                }
+            };
+            return Ok(Some(command));
+        }
+
+        // Every argument is a whole number
+        let mut args = Vec::new();
+        let mut spans = Vec::new();
+        let mut end = span.end;
+        for (word, span) in words {
+            let n = std::str::from_utf8(word)
+                .ok()
+                .and_then(|s| s.parse::<usize>().ok())
+                .ok_or_else(|| error(ParseErrorKind::NotANumber(word.to_vec()), span.clone()))?;
+            args.push(n);
+            end = span.end;
+            spans.push(span);
+        }
+
+        let command = match (spec.name, args.as_slice()) {
+            (b"MOVE" | b"LASER" | b"TORPEDO", [0, ..]) => {
+                return Err(error(ParseErrorKind::Zero, spans[0].clone()))
+            }
+            (b"HELP", []) => Help,
+            (b"MOVE", &[speed, x, y]) => Move {
+                speed,
+                x,
+                y,
+                system: None,
+            },
+            (b"MOVE", &[speed, x, y, xx, yy]) => Move {
+                speed,
+                x,
+                y,
+                system: Some((xx, yy)),
+            },
+            (b"LASER", &[energy, x, y]) => Laser { energy, x, y },
+            (b"TORPEDO", &[number, x, y]) => Torpedo { number, x, y },
+            (b"SHIELDS", &[energy]) => Shields { energy },
+            (b"SCAN", []) => Scan,
+            (b"SURVEY", []) => Survey,
+            (b"INVESTIGATE", []) => Investigate,
+            (b"DOCK", []) => Dock,
+            (b"DAMAGE", []) => Damage,
+            (b"LOG", []) => Log { page: None },
+            (b"LOG", &[page]) => Log { page: Some(page) },
+            (b"MACROS", []) => Macros,
+            (b"QUIT", []) => Quit,
+            _ => {
+                // Wrong number of arguments: point just past the end
+                // for a missing one, otherwise at the first extra one
+                let max = spec.arg_names().count();
+                return Err(match spec.arg_names().nth(args.len()) {
+                    Some(name) => error(ParseErrorKind::Missing(name), (end + 1)..(end + 1)),
+                    None => error(
+                        ParseErrorKind::Unexpected,
+                        spans.get(max).cloned().unwrap_or(span),
+                    ),
+                });
+            }
+        };
+        Ok(Some(command))
+    }
+}
+
+/// Chains of commands defined by the captain, run by name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Macros {
+    defs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Macros {
+    /// Returns the body of the named macro, ignoring case
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        self.defs
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, body)| body.as_slice())
+    }
+
+    /// Defines (or redefines) a macro, or deletes it if the body is empty
+    pub fn define(&mut self, name: &[u8], body: &[u8]) {
+        let name = name.to_ascii_uppercase();
+        match self.defs.iter_mut().find(|(n, _)| *n == name) {
+            _ if body.is_empty() => self.defs.retain(|(n, _)| *n != name),
+            Some(def) => def.1 = body.to_vec(),
+            None => self.defs.push((name, body.to_vec())),
+        }
+    }
+
+    /// Iterates over the macros in the order they were defined
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.defs.iter().map(|(n, b)| (n.as_slice(), b.as_slice()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+}
+
+/// Other names for built-in commands, given by the captain
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aliases {
+    defs: Vec<(Vec<u8>, &'static CommandSpec)>,
+}
+
+impl Aliases {
+    /// Returns the command the name stands for, ignoring case
+    pub fn get(&self, name: &[u8]) -> Option<&'static CommandSpec> {
+        self.defs
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, spec)| spec)
+    }
+
+    /// Lets the name stand for the command, replacing any alias
+    /// of the same name
+    pub fn define(&mut self, name: &[u8], command: &'static CommandSpec) {
+        let name = name.to_ascii_uppercase();
+        match self.defs.iter_mut().find(|(n, _)| *n == name) {
+            Some(def) => def.1 = command,
+            None => self.defs.push((name, command)),
+        }
+    }
+
+    /// Removes an alias, returning whether it existed
+    pub fn remove(&mut self, name: &[u8]) -> bool {
+        let len = self.defs.len();
+        self.defs.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.defs.len() < len
+    }
+
+    /// Iterates over the aliases in the order they were defined
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &'static CommandSpec)> {
+        self.defs.iter().map(|(n, spec)| (n.as_slice(), *spec))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+}
+
+/// Parses a line of `;`-separated commands, expanding any macros
+/// and aliases. Returns no commands if the line is blank
+pub fn parse_line(
+    line: &[u8],
+    macros: &Macros,
+    aliases: &Aliases,
+) -> Result<Vec<Command>, ParseError> {
+    let mut commands = Vec::new();
+    let mut start = 0;
+    while start < line.len() {
+        let end = segment_end(line, start, aliases);
+        let segment = &line[start..end];
+        let mut words = words(segment);
+        match words.next() {
+            Some((name, span)) if macros.get(name).is_some() => {
+                if let Some((_, extra)) = words.next() {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::Unexpected,
+                        span: extra,
+                        spec: None,
+                    }
+                    .offset(start));
+                }
+                let body = macros.get(name).unwrap_or_default();
+                let expanded = parse_line(body, &Macros::default(), &Aliases::default())
+                    .map_err(|err| ParseError { span, ..err }.offset(start))?;
+                commands.extend(expanded);
+            }
+            first => {
+                let command =
+                    Command::parse_with(segment, aliases).map_err(|err| err.offset(start))?;
+                // Macros and aliases share the same names
+                let clash = match &command {
+                    Some(Command::Define { name, .. }) if aliases.get(name).is_some() => {
+                        Some((name, &b"DEFINE"[..]))
+                    }
+                    Some(Command::Alias { name, .. }) if macros.get(name).is_some() => {
+                        Some((name, &b"ALIAS"[..]))
+                    }
+                    _ => None,
+                };
+                if let Some((name, by)) = clash {
+                    // The name is the first thing after the command word
+                    let after = first.map_or(0, |(_, span)| span.end);
+                    let from = segment[after..]
+                        .iter()
+                        .position(|c| !c.is_ascii_whitespace())
+                        .map_or(segment.len(), |i| after + i);
+                    return Err(ParseError {
+                        kind: ParseErrorKind::Taken(name.clone()),
+                        span: from..(from + name.len()),
+                        spec: CommandSpec::find(by),
+                    }
+                    .offset(start));
+                }
+                commands.extend(command);
+            }
+        }
+        start = end + 1;
+    }
+    Ok(commands)
+}
+
+/// Finds the end of the command starting at the given position
+/// in a line of `;`-separated commands
+fn segment_end(line: &[u8], start: usize, aliases: &Aliases) -> usize {
+    // A DEFINE takes the rest of the line as the macro body
+    let rest = &line[start..];
+    match words(rest).next() {
+        Some((word, _))
+            if CommandSpec::find(word)
+                .or_else(|| aliases.get(word))
+                .is_some_and(|s| s.name == b"DEFINE") =>
+        {
+            line.len()
+        }
+        _ => rest
+            .iter()
+            .position(|&c| c == b';')
+            .map_or(line.len(), |i| start + i),
+    }
+}
+
+/// Names of the commands, macros and aliases starting with
+/// the given text, ignoring case
+pub fn completions(prefix: &[u8], macros: &Macros, aliases: &Aliases) -> Vec<Vec<u8>> {
+    COMMANDS
+        .iter()
+        .filter(|spec| !spec.help.is_empty())
+        .map(|spec| spec.name)
+        .chain(macros.iter().map(|(name, _)| name))
+        .chain(aliases.iter().map(|(name, _)| name))
+        .filter(|name| {
+            name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix)
+        })
+        .map(<[u8]>::to_vec)
+        .collect()
+}
+
+/// The arguments still to be given to the last command in the line,
+/// e.g. `x y [X Y]` after `MOVE 3`, or nothing if it is not known
+pub fn hint(line: &[u8], aliases: &Aliases) -> Vec<u8> {
+    let mut start = 0;
+    loop {
+        let end = segment_end(line, start, aliases);
+        if end >= line.len() {
+            break;
+        }
+        start = end + 1;
+    }
+
+    let mut given = words(&line[start..]);
+    let Some(spec) = given
+        .next()
+        .and_then(|(word, _)| CommandSpec::find(word).or_else(|| aliases.get(word)))
+    else {
+        return Vec::new();
+    };
+    // Within a DEFINE, hint at the commands of the macro body
+    if let (b"DEFINE", Some(eq)) = (spec.name, line[start..].iter().position(|&c| c == b'=')) {
+        return hint(&line[(start + eq + 1)..], &Aliases::default());
+    }
+    let rest: Vec<&[u8]> = words(spec.args)
+        .map(|(word, _)| word)
+        .skip(given.count())
+        .collect();
+    let mut hint = rest.join(&b' ');
+
+    // Part way through the optional arguments, so reopen the bracket
+    let open = hint.iter().filter(|&&c| c == b'[').count();
+    if hint.iter().filter(|&&c| c == b']').count() > open {
+        hint.insert(0, b'[');
+    }
+    hint
+}
+
+/// Parses the `name = c; ...` following a DEFINE, which ends at the
+/// given position in the line
+fn parse_define(line: &[u8], from: usize) -> Result<Command, (ParseErrorKind, Range<usize>)> {
+    use ParseErrorKind::*;
+    let rest = &line[from..];
+    let (head, body, eq) = match rest.iter().position(|&c| c == b'=') {
+        Some(i) => (&rest[..i], &rest[(i + 1)..], from + i),
+        None => (rest, &rest[rest.len()..], line.len()),
+    };
+
+    // The name is a single word before the equals sign
+    let mut names = words(head);
+    let (name, span) = match names.next() {
+        Some((name, span)) => (name, (span.start + from)..(span.end + from)),
+        None => return Err((Missing(b"name"), eq..(eq + 1))),
+    };
+    if eq == line.len() {
+        return Err((Expected(b"="), (span.end + 1)..(span.end + 1)));
+    }
+    if let Some((_, extra)) = names.next() {
+        let span = (span.start)..(extra.end + from);
+        return Err((BadName(line[span.clone()].to_vec()), span));
+    }
+    check_name(name, span)?;
+
+    // The body may only use built-in commands
+    let start = eq + 1;
+    let commands = parse_line(body, &Macros::default(), &Aliases::default()).map_err(|err| {
+        let err = err.offset(start);
+        (err.kind, err.span)
+    })?;
+    if commands.iter().any(|c| matches!(c, Command::Define { .. })) {
+        // Arguments are all numbers, so the word must be the command
+        let nested = words(body)
+            .find(|(word, _)| CommandSpec::find(word).is_some_and(|s| s.name == b"DEFINE"))
+            .map_or(0..body.len(), |(_, span)| span);
+        return Err((Nested, (nested.start + start)..(nested.end + start)));
+    }
+
+    Ok(Command::Define {
+        name: name.to_ascii_uppercase(),
+        body: body.trim_ascii().to_vec(),
+    })
+}
+
+/// Checks a name given by the captain is a word that
+/// does not clash with a built-in command
+fn check_name(name: &[u8], span: Range<usize>) -> Result<(), (ParseErrorKind, Range<usize>)> {
+    if !name[0].is_ascii_alphabetic() || !name.iter().all(u8::is_ascii_alphanumeric) {
+        return Err((ParseErrorKind::BadName(name.to_vec()), span));
+    }
+    if CommandSpec::find(name).is_some() {
+        return Err((ParseErrorKind::Reserved(name.to_ascii_uppercase()), span));
+    }
+    Ok(())
+}
+
+/// Splits a line into words on whitespace, with their positions
+fn words(line: &[u8]) -> impl Iterator<Item = (&[u8], Range<usize>)> {
+    let mut start = 0;
+    line.split(|c| c.is_ascii_whitespace())
+        .filter_map(move |word| {
+            let span = start..(start + word.len());
+            start += word.len() + 1;
+            (!word.is_empty()).then_some((word, span))
+        })
+}
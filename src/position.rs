@@ -0,0 +1,313 @@
+use crate::{bconcat, DisplayBytes, Options};
+use rand::Rng;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Reasons a co-ordinate could not be constructed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoordError {
+    /// Sector (x, y) lies outside a system with the given
+    /// number of sectors on a side
+    Sector(usize, usize, usize),
+    /// System (X, Y) lies outside a galaxy with the given
+    /// number of systems on a side
+    System(usize, usize, usize),
+}
+
+impl DisplayBytes for CoordError {
+    fn display_bytes(&self) -> Vec<u8> {
+        use CoordError::*;
+        let (kind, x, y, size): (&[u8], _, _, _) = match *self {
+            Sector(x, y, size) => (b"SECTOR", x, y, size),
+            System(x, y, size) => (b"SYSTEM", x, y, size),
+        };
+        bconcat!(
+            kind,
+            b" (",
+            x,
+            b", ",
+            y,
+            b") is off the charts!\n",
+            kind,
+            b" co-ordinates run from 0 to ",
+            size - 1,
+            b"."
+        )
+        .to_vec()
+    }
+}
+
+/// The co-ordinate of a sector within a system
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SectorCoord {
+    x: usize,
+    y: usize,
+}
+
+/// The co-ordinate of a system within the galaxy
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SystemCoord {
+    x: usize,
+    y: usize,
+}
+
+/// A location in the galaxy: a sector within a system
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub sector: SectorCoord,
+    pub system: SystemCoord,
+}
+
+impl SectorCoord {
+    /// Creates a sector co-ordinate, checking it lies within
+    /// a system of the given dimensions
+    pub fn new(x: usize, y: usize, options: &Options) -> Result<Self, CoordError> {
+        if x < options.sectors && y < options.sectors {
+            Ok(Self { x, y })
+        } else {
+            Err(CoordError::Sector(x, y, options.sectors))
+        }
+    }
+
+    /// Returns the column of the sector within its system
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    /// Returns the row of the sector within its system
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    /// Iterates over every sector in a system, in row-major order
+    pub fn all(options: &Options) -> impl Iterator<Item = Self> {
+        grid(options.sectors).map(|(x, y)| Self { x, y })
+    }
+
+    /// Iterates over the sectors surrounding this one
+    pub fn adjacent(&self, options: &Options) -> impl Iterator<Item = Self> {
+        let (x, y) = (self.x, self.y);
+        let xs = x.saturating_sub(1)..(x + 2).min(options.sectors);
+        let ys = y.saturating_sub(1)..(y + 2).min(options.sectors);
+
+        let mut coords = Vec::new();
+        for i in xs {
+            for j in ys.clone() {
+                if (i, j) != (x, y) {
+                    coords.push(Self { x: i, y: j });
+                }
+            }
+        }
+        coords.into_iter().rev()
+    }
+
+    /// Straight-line distance to another sector
+    pub fn distance(&self, other: Self) -> f64 {
+        distance((self.x, self.y), (other.x, other.y))
+    }
+
+    /// Moves up to `speed` sectors towards the target, returning
+    /// the sector reached and the distance travelled
+    pub fn step_towards(self, target: Self, speed: usize) -> (Self, f64) {
+        let ((x, y), dr) = step((self.x, self.y), (target.x, target.y), speed);
+        (Self { x, y }, dr)
+    }
+}
+
+/// Shortest routes from one sector to every other that can be reached
+/// through open space, moving to an adjacent sector at each step
+pub struct Routes {
+    sectors: usize,
+    /// The sector each is entered from on its route, and the
+    /// number of steps taken, for those that can be reached
+    previous: Vec<Option<(SectorCoord, usize)>>,
+}
+
+impl Routes {
+    /// Searches outwards from a sector through those that are open
+    pub fn search(
+        from: SectorCoord,
+        options: &Options,
+        open: impl Fn(SectorCoord) -> bool,
+    ) -> Self {
+        let sectors = options.sectors;
+        let mut routes = Self {
+            sectors,
+            previous: vec![None; sectors * sectors],
+        };
+        let start = routes.slot(from);
+        routes.previous[start] = Some((from, 0));
+
+        let mut queue = VecDeque::from([from]);
+        while let Some(sector) = queue.pop_front() {
+            let steps = routes.steps(sector).unwrap_or_default() + 1;
+            for next in sector.adjacent(options) {
+                let slot = routes.slot(next);
+                if routes.previous[slot].is_none() && open(next) {
+                    routes.previous[slot] = Some((sector, steps));
+                    queue.push_back(next);
+                }
+            }
+        }
+        routes
+    }
+
+    fn slot(&self, sector: SectorCoord) -> usize {
+        sector.x + self.sectors * sector.y
+    }
+
+    /// The number of steps to a sector, if it can be reached
+    pub fn steps(&self, to: SectorCoord) -> Option<usize> {
+        self.previous[self.slot(to)].map(|(_, steps)| steps)
+    }
+
+    /// Iterates over the sectors that can be reached, including the
+    /// starting sector, in row-major order
+    pub fn reachable(&self) -> impl Iterator<Item = SectorCoord> + '_ {
+        grid(self.sectors)
+            .map(|(x, y)| SectorCoord { x, y })
+            .filter(|&s| self.steps(s).is_some())
+    }
+
+    /// The sectors passed through on the way to a sector, ending
+    /// with it, if it can be reached
+    pub fn route(&self, to: SectorCoord) -> Option<Vec<SectorCoord>> {
+        let mut route = Vec::new();
+        let mut sector = to;
+        while let Some((previous, steps)) = self.previous[self.slot(sector)] {
+            if steps == 0 {
+                route.reverse();
+                return Some(route);
+            }
+            route.push(sector);
+            sector = previous;
+        }
+        None
+    }
+}
+
+impl SystemCoord {
+    /// Creates a system co-ordinate, checking it lies within
+    /// a galaxy of the given dimensions
+    pub fn new(x: usize, y: usize, options: &Options) -> Result<Self, CoordError> {
+        if x < options.systems && y < options.systems {
+            Ok(Self { x, y })
+        } else {
+            Err(CoordError::System(x, y, options.systems))
+        }
+    }
+
+    /// Returns the column of the system within the galaxy
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    /// Returns the row of the system within the galaxy
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    /// Iterates over every system in the galaxy, in row-major order
+    pub fn all(options: &Options) -> impl Iterator<Item = Self> {
+        grid(options.systems).map(|(x, y)| Self { x, y })
+    }
+
+    /// Moves up to `speed` systems towards the target, returning
+    /// the system reached and the distance travelled
+    pub fn step_towards(self, target: Self, speed: usize) -> (Self, f64) {
+        let ((x, y), dr) = step((self.x, self.y), (target.x, target.y), speed);
+        (Self { x, y }, dr)
+    }
+
+    /// The range of galaxy indices holding this system's sectors
+    pub(crate) fn cells(&self, options: &Options) -> Range<usize> {
+        let start = Position {
+            sector: SectorCoord { x: 0, y: 0 },
+            system: *self,
+        }
+        .index(options);
+        start..(start + options.sectors * options.sectors)
+    }
+}
+
+impl Position {
+    /// Creates a position from sector (x, y) and system (X, Y)
+    /// co-ordinates, checking both lie within the galaxy
+    pub fn new(
+        x: usize,
+        y: usize,
+        xx: usize,
+        yy: usize,
+        options: &Options,
+    ) -> Result<Self, CoordError> {
+        Ok(Self {
+            sector: SectorCoord::new(x, y, options)?,
+            system: SystemCoord::new(xx, yy, options)?,
+        })
+    }
+
+    /// Picks a position anywhere in the galaxy
+    pub(crate) fn random<R: Rng + ?Sized>(rng: &mut R, options: &Options) -> Self {
+        Self {
+            sector: SectorCoord {
+                x: rng.gen_range(0..options.sectors),
+                y: rng.gen_range(0..options.sectors),
+            },
+            system: SystemCoord {
+                x: rng.gen_range(0..options.systems),
+                y: rng.gen_range(0..options.systems),
+            },
+        }
+    }
+
+    /// The index of this position in the galaxy
+    pub(crate) fn index(&self, options: &Options) -> usize {
+        let (sectors, systems) = (options.sectors, options.systems);
+        self.sector.x
+            + sectors * (self.sector.y + sectors * (self.system.x + systems * self.system.y))
+    }
+}
+
+impl From<Position> for (usize, usize, usize, usize) {
+    fn from(p: Position) -> Self {
+        (p.sector.x, p.sector.y, p.system.x, p.system.y)
+    }
+}
+
+impl DisplayBytes for SectorCoord {
+    fn display_bytes(&self) -> Vec<u8> {
+        bconcat!(b"(", self.x, b", ", self.y, b")").to_vec()
+    }
+}
+
+impl DisplayBytes for SystemCoord {
+    fn display_bytes(&self) -> Vec<u8> {
+        bconcat!(b"(", self.x, b", ", self.y, b")").to_vec()
+    }
+}
+
+/// Co-ordinates of a square grid with the given side, in row-major order
+fn grid(size: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..size).flat_map(move |y| (0..size).map(move |x| (x, y)))
+}
+
+fn distance(a: (usize, usize), b: (usize, usize)) -> f64 {
+    (a.0.abs_diff(b.0) as f64).hypot(a.1.abs_diff(b.1) as f64)
+}
+
+/// Moves up to `speed` units in a straight line from one grid point
+/// towards another, rounding to the nearest grid point. The result
+/// never leaves the box spanned by the two points, so stays in range
+fn step(from: (usize, usize), to: (usize, usize), speed: usize) -> ((usize, usize), f64) {
+    let dr = distance(from, to);
+    if speed as f64 >= dr {
+        return (to, dr);
+    }
+
+    let towards = |a: usize, b: usize| {
+        let d = (speed as f64 * (b as f64 - a as f64) / dr).round();
+        let n = (a as f64 + d) as usize;
+        n.clamp(a.min(b), a.max(b))
+    };
+    ((towards(from.0, to.0), towards(from.1, to.1)), speed as f64)
+}
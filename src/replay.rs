@@ -0,0 +1,111 @@
+use crate::{
+    save::{header, seal, unseal},
+    LoadError, Options, Persist,
+};
+
+/// Identifies a replay
+const MAGIC: &[u8] = b"STRR";
+
+/// Version of the replay format, to be bumped whenever its
+/// layout or the game rules change after it has been released
+pub const REPLAY_VERSION: u8 = 1;
+
+/// A recording of every command entered during a game. Together
+/// with the seed and options this is enough to reproduce the game
+/// exactly
+#[derive(Clone, PartialEq)]
+pub struct Replay {
+    seed: u64,
+    options: Options,
+    commands: Vec<Vec<u8>>,
+    next: usize,
+}
+
+impl Replay {
+    /// Starts an empty recording for a game with the given
+    /// seed and options
+    pub fn new(seed: u64, options: Options) -> Self {
+        Self {
+            seed,
+            options,
+            commands: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Returns the seed of the recorded game
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the options of the recorded game
+    pub fn options(&self) -> Options {
+        self.options
+    }
+
+    /// Returns the number of recorded commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns whether no commands have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Appends a command line to the recording, dropping
+    /// any trailing whitespace
+    pub fn push(&mut self, command: &[u8]) {
+        let end = command
+            .iter()
+            .rposition(|c| !c.is_ascii_whitespace())
+            .map_or(0, |i| i + 1);
+        self.commands.push(command[..end].to_vec());
+    }
+
+    /// Returns the next command to play back, if any remain
+    pub fn peek(&self) -> Option<&[u8]> {
+        self.commands.get(self.next).map(Vec::as_slice)
+    }
+
+    /// Advances playback past the current command
+    pub fn advance(&mut self) {
+        self.next += 1;
+    }
+
+    /// Serialises the recording to the binary replay format
+    pub fn export(&self) -> Vec<u8> {
+        let mut out = header(MAGIC, REPLAY_VERSION);
+        self.write(&mut out);
+        seal(&mut out);
+        out
+    }
+
+    /// Restores a recording from the binary replay format,
+    /// ready to be played back from the start
+    pub fn import(data: &[u8]) -> Result<Self, LoadError> {
+        let mut input = unseal(data, MAGIC, REPLAY_VERSION)?;
+        let replay = Self::read(&mut input)?;
+        if !input.is_empty() {
+            return Err(LoadError::Corrupt);
+        }
+        Ok(replay)
+    }
+}
+
+impl Persist for Replay {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.seed.write(out);
+        self.options.write(out);
+        self.commands.write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        Ok(Self {
+            seed: u64::read(input)?,
+            options: Options::read(input)?,
+            commands: Vec::read(input)?,
+            next: 0,
+        })
+    }
+}
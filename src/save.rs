@@ -0,0 +1,414 @@
+use crate::{
+    bconcat, parse_line, Aliases, Capacity, Command, Difficulty, DisplayBytes, Entity, Macros,
+    Options, Ship, Subsystem, Systems, Weapon,
+};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Identifies a saved game
+pub(crate) const MAGIC: &[u8] = b"STRP";
+
+/// Version of the save format, to be bumped whenever the layout
+/// of the saved game state changes after it has been released
+pub const SAVE_VERSION: u8 = 1;
+
+/// Reasons a saved game could not be restored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadError {
+    /// Not the expected kind of data at all
+    Magic,
+    /// Written by an incompatible version of the game
+    /// (found, expected)
+    Version(u8, u8),
+    /// The data ended unexpectedly
+    Truncated,
+    /// The data is damaged or contains impossible values
+    Corrupt,
+}
+
+impl DisplayBytes for LoadError {
+    fn display_bytes(&self) -> Vec<u8> {
+        use LoadError::*;
+        match self {
+            Magic => b"Data is not in a recognised format.".to_vec(),
+            Version(found, expected) => bconcat!(
+                b"Data has version ",
+                found,
+                b", but this game expects version ",
+                expected,
+                b"."
+            )
+            .to_vec(),
+            Truncated => b"Data is incomplete.".to_vec(),
+            Corrupt => b"Data is corrupted.".to_vec(),
+        }
+    }
+}
+
+/// Conversion to and from the binary save format
+pub trait Persist: Sized {
+    /// Appends the binary representation to the output
+    fn write(&self, out: &mut Vec<u8>);
+
+    /// Consumes the binary representation from the front of the input
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError>;
+}
+
+/// Splits off the first n bytes of the input
+pub(crate) fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], LoadError> {
+    if input.len() < n {
+        return Err(LoadError::Truncated);
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+/// Simple FNV-1a hash used to detect damaged saves
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c9dc5, |hash, &c| {
+        (hash ^ c as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// Starts a blob of the given kind and version
+pub(crate) fn header(magic: &[u8], version: u8) -> Vec<u8> {
+    let mut out = magic.to_vec();
+    out.push(version);
+    out
+}
+
+/// Finishes a blob by appending its checksum
+pub(crate) fn seal(out: &mut Vec<u8>) {
+    let sum = checksum(out);
+    sum.write(out);
+}
+
+/// Checks the kind, version and integrity of a blob,
+/// returning its contents
+pub(crate) fn unseal<'a>(data: &'a [u8], magic: &[u8], version: u8) -> Result<&'a [u8], LoadError> {
+    let mut input = data;
+    if take(&mut input, magic.len()) != Ok(magic) {
+        return Err(LoadError::Magic);
+    }
+
+    let found = u8::read(&mut input)?;
+    if found != version {
+        return Err(LoadError::Version(found, version));
+    }
+
+    if input.len() < 4 {
+        return Err(LoadError::Truncated);
+    }
+    let (input, mut sum) = input.split_at(input.len() - 4);
+    if checksum(&data[..(data.len() - 4)]) != u32::read(&mut sum)? {
+        return Err(LoadError::Corrupt);
+    }
+
+    Ok(input)
+}
+
+macro_rules! persist_int {
+    ($($t:ty),*) => {
+        $(
+            impl Persist for $t {
+                fn write(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+                    let bytes = take(input, std::mem::size_of::<$t>())?;
+                    Ok(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+persist_int!(u8, u16, u32, u64, u128);
+
+impl Persist for usize {
+    // Stored as 64 bits so that saves are portable between
+    // native and wasm32 builds
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u64).write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        usize::try_from(u64::read(input)?).map_err(|_| LoadError::Corrupt)
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(t) => {
+                out.push(1);
+                t.write(out);
+            }
+        }
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        match u8::read(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::read(input)?)),
+            _ => Err(LoadError::Corrupt),
+        }
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.len().write(out);
+        for t in self {
+            t.write(out);
+        }
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        // Deliberately not pre-allocating, as the length may be bogus
+        let len = usize::read(input)?;
+        let mut v = Vec::new();
+        for _ in 0..len {
+            v.push(T::read(input)?);
+        }
+        Ok(v)
+    }
+}
+
+impl<T: Persist + Eq + Hash> Persist for HashSet<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.len().write(out);
+        for t in self {
+            t.write(out);
+        }
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        Ok(Vec::<T>::read(input)?.into_iter().collect())
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.0.write(out);
+        self.1.write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        Ok((A::read(input)?, B::read(input)?))
+    }
+}
+
+impl<A: Persist, B: Persist, C: Persist, D: Persist> Persist for (A, B, C, D) {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.0.write(out);
+        self.1.write(out);
+        self.2.write(out);
+        self.3.write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        Ok((
+            A::read(input)?,
+            B::read(input)?,
+            C::read(input)?,
+            D::read(input)?,
+        ))
+    }
+}
+
+impl Persist for Ship {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.energy.write(out);
+        self.shields.write(out);
+        self.torpedoes.write(out);
+        self.range.write(out);
+        self.speed.write(out);
+        self.capacity.energy.write(out);
+        self.capacity.shields.write(out);
+        self.capacity.torpedoes.write(out);
+        self.tuned.write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        let ship = Self {
+            energy: u16::read(input)?,
+            shields: u16::read(input)?,
+            torpedoes: u16::read(input)?,
+            range: u16::read(input)?,
+            speed: u16::read(input)?,
+            capacity: Capacity {
+                energy: u16::read(input)?,
+                shields: u16::read(input)?,
+                torpedoes: u16::read(input)?,
+            },
+            tuned: Persist::read(input)?,
+        };
+        if !ship.is_valid() {
+            return Err(LoadError::Corrupt);
+        }
+        Ok(ship)
+    }
+}
+
+impl Persist for Weapon {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            Weapon::Lasers => 0,
+            Weapon::Torpedoes => 1,
+        });
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        match u8::read(input)? {
+            0 => Ok(Weapon::Lasers),
+            1 => Ok(Weapon::Torpedoes),
+            _ => Err(LoadError::Corrupt),
+        }
+    }
+}
+
+impl Persist for Systems {
+    fn write(&self, out: &mut Vec<u8>) {
+        for time in self.repair_times() {
+            time.write(out);
+        }
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        let mut repairs = [0; Subsystem::ALL.len()];
+        for time in repairs.iter_mut() {
+            *time = u8::read(input)?;
+        }
+        Ok(Self::new(repairs))
+    }
+}
+
+impl Persist for Difficulty {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.value().write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        Ok(Difficulty::from_value(u8::read(input)?))
+    }
+}
+
+impl Persist for Options {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.difficulty.write(out);
+        self.systems.write(out);
+        self.sectors.write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        let options = Self {
+            difficulty: Difficulty::read(input)?,
+            systems: usize::read(input)?,
+            sectors: usize::read(input)?,
+        };
+        if options.clamped() != options {
+            return Err(LoadError::Corrupt);
+        }
+        Ok(options)
+    }
+}
+
+impl Persist for Entity {
+    fn write(&self, out: &mut Vec<u8>) {
+        use Entity::*;
+        match self {
+            BlackHole => out.push(0),
+            Star => out.push(1),
+            Planet => out.push(2),
+            Base => out.push(3),
+            Klargons(ship) => {
+                out.push(4);
+                ship.write(out);
+            }
+            Remulins(ship) => {
+                out.push(5);
+                ship.write(out);
+            }
+            Faringa(ship) => {
+                out.push(6);
+                ship.write(out);
+            }
+            Berg(ship) => {
+                out.push(7);
+                ship.write(out);
+            }
+        }
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        use Entity::*;
+        Ok(match u8::read(input)? {
+            0 => BlackHole,
+            1 => Star,
+            2 => Planet,
+            3 => Base,
+            4 => Klargons(Ship::read(input)?),
+            5 => Remulins(Ship::read(input)?),
+            6 => Faringa(Ship::read(input)?),
+            7 => Berg(Ship::read(input)?),
+            _ => return Err(LoadError::Corrupt),
+        })
+    }
+}
+
+impl Persist for Macros {
+    fn write(&self, out: &mut Vec<u8>) {
+        let defs: Vec<(Vec<u8>, Vec<u8>)> =
+            self.iter().map(|(n, b)| (n.to_vec(), b.to_vec())).collect();
+        defs.write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        // Each macro must be one the captain could have defined
+        let mut macros = Macros::default();
+        for (name, body) in Vec::<(Vec<u8>, Vec<u8>)>::read(input)? {
+            let line = bconcat!(b"DEFINE ", name.as_slice(), b" = ", body.as_slice());
+            match parse_line(line, &Macros::default(), &Aliases::default()).as_deref() {
+                Ok([Command::Define { name: n, body: b }])
+                    if *n == name && *b == body && !b.is_empty() && macros.get(n).is_none() =>
+                {
+                    macros.define(n, b)
+                }
+                _ => return Err(LoadError::Corrupt),
+            }
+        }
+        Ok(macros)
+    }
+}
+
+impl Persist for Aliases {
+    fn write(&self, out: &mut Vec<u8>) {
+        let defs: Vec<(Vec<u8>, Vec<u8>)> = self
+            .iter()
+            .map(|(n, spec)| (n.to_vec(), spec.name.to_vec()))
+            .collect();
+        defs.write(out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, LoadError> {
+        // Each alias must be one the captain could have given
+        let mut aliases = Aliases::default();
+        for (name, command) in Vec::<(Vec<u8>, Vec<u8>)>::read(input)? {
+            let line = bconcat!(b"ALIAS ", name.as_slice(), b" ", command.as_slice());
+            match Command::parse(line) {
+                Ok(Some(Command::Alias {
+                    name: n,
+                    command: spec,
+                })) if n == name && spec.name == command && aliases.get(&n).is_none() => {
+                    aliases.define(&n, spec)
+                }
+                _ => return Err(LoadError::Corrupt),
+            }
+        }
+        Ok(aliases)
+    }
+}
@@ -1,25 +1,59 @@
-use crate::{bconcat, constants::*, index, nearby, DisplayBytes, Entity, Ship, Terminal};
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use crate::{
+    bconcat, completions,
+    constants::*,
+    hint, nearby, parse_line,
+    save::{header, seal, unseal, MAGIC},
+    Aliases, Attribute, Colour, Command, CommandSpec, DisplayBytes, Entity, LoadError, Macros,
+    Options, ParseError, ParseErrorKind, Persist, Position, Replay, Routes, SectorCoord, Ship,
+    Subsystem, SystemCoord, Systems, Terminal, Weapon, COMMANDS, SAVE_VERSION,
+};
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::collections::HashSet;
 
 /// The current state of the game including the player stats
 /// and the state of all entities throughout the galaxy
 pub struct GameState {
-    galaxy: [Option<Entity>; SYSTEMS * SYSTEMS * SECTORS * SECTORS],
+    galaxy: Vec<Option<Entity>>,
     logbook: Vec<Vec<u8>>,
     last_entry: Vec<u8>,
     page: usize,
-    position: (usize, usize, usize, usize),
-    visited: HashSet<(usize, usize, usize, usize)>,
+    position: Position,
+    visited: HashSet<Position>,
     player: Ship,
+    /// The spirits of the crew, out of MORALE
+    morale: u8,
+    /// Damage to the player's subsystems awaiting repair
+    systems: Systems,
     mission: usize,
     date: usize,
+    seed: u64,
+    options: Options,
+    macros: Macros,
+    aliases: Aliases,
+    rng: ChaCha8Rng,
+    replay: Option<Replay>,
 }
 
 impl GameState {
-    /// Initialises a new game state
+    /// Initialises a new game state with a random seed
     pub fn new() -> Self {
-        let mut galaxy = generate_galaxy();
+        Self::new_with_seed(thread_rng().gen())
+    }
+
+    /// Initialises a new game state from the given seed
+    /// with the default options
+    pub fn new_with_seed(seed: u64) -> Self {
+        Self::new_with_options(seed, Options::default())
+    }
+
+    /// Initialises a new game state from the given seed and options.
+    /// Games started from the same seed and options are identical
+    /// given the same sequence of commands
+    pub fn new_with_options(seed: u64, options: Options) -> Self {
+        let options = options.clamped();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut galaxy = generate_galaxy(&mut rng, options);
         let logbook = vec![Vec::new()];
         let visited = HashSet::new();
         let last_entry = b"COMPUTER ERROR: NO ENTRY AVAILABLE".to_vec();
@@ -27,14 +61,8 @@ impl GameState {
         let mission = 0;
         let date = 0;
 
-        let mut rng = thread_rng();
-        let position = (
-            rng.gen_range(0..SECTORS),
-            rng.gen_range(0..SECTORS),
-            rng.gen_range(0..SYSTEMS),
-            rng.gen_range(0..SYSTEMS),
-        );
-        galaxy[index!(position)] = None;
+        let position = Position::random(&mut rng, &options);
+        galaxy[position.index(&options)] = None;
 
         Self {
             galaxy,
@@ -43,370 +71,617 @@ impl GameState {
             page,
             position,
             visited,
-            player: Ship {
-                energy: 255,
-                shields: 255,
-                torpedoes: 5,
-                range: 7,
-            },
+            player: Ship::player(),
+            morale: MORALE,
+            systems: Systems::default(),
             date,
             mission,
+            seed,
+            options,
+            macros: Macros::default(),
+            aliases: Aliases::default(),
+            rng,
+            replay: None,
+        }
+    }
+
+    /// Returns the seed used to generate this game
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the options this game was started with
+    pub fn options(&self) -> Options {
+        self.options
+    }
+
+    /// Starts recording every command line into a replay. This
+    /// fails if the game has already progressed, as the recording
+    /// could then no longer reproduce the game from its seed
+    pub fn start_recording(&mut self) -> bool {
+        if self.date > 0 || self.logbook.len() > 1 || !self.logbook[0].is_empty() {
+            return false;
+        }
+        self.replay = Some(Replay::new(self.seed, self.options));
+        true
+    }
+
+    /// Returns the commands recorded so far, if recording
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+
+    /// Returns the number of enemies that must be defeated to win
+    pub fn goal(&self) -> usize {
+        self.options.mission()
+    }
+
+    /// Returns the crew's morale, out of MORALE
+    pub fn morale(&self) -> u8 {
+        self.morale
+    }
+
+    /// Returns the damage to the player's subsystems
+    pub fn systems(&self) -> Systems {
+        self.systems
+    }
+
+    /// Returns the number of time periods elapsed
+    pub fn date(&self) -> usize {
+        self.date
+    }
+
+    /// Serialises the full game state, including the state of
+    /// the random number generator, to the binary save format
+    pub fn save(&self) -> Vec<u8> {
+        let mut out = header(MAGIC, SAVE_VERSION);
+
+        self.options.write(&mut out);
+        self.galaxy.write(&mut out);
+        self.logbook.write(&mut out);
+        self.last_entry.write(&mut out);
+        self.page.write(&mut out);
+        <(usize, usize, usize, usize)>::from(self.position).write(&mut out);
+        // Sorted so that the same game always saves the same way
+        let mut visited: Vec<(usize, usize, usize, usize)> =
+            self.visited.iter().map(|&p| p.into()).collect();
+        visited.sort();
+        visited.write(&mut out);
+        self.player.write(&mut out);
+        self.morale.write(&mut out);
+        self.systems.write(&mut out);
+        self.mission.write(&mut out);
+        self.date.write(&mut out);
+        self.seed.write(&mut out);
+        self.macros.write(&mut out);
+        self.aliases.write(&mut out);
+        for b in self.rng.get_seed() {
+            b.write(&mut out);
+        }
+        self.rng.get_stream().write(&mut out);
+        self.rng.get_word_pos().write(&mut out);
+        self.replay.write(&mut out);
+
+        seal(&mut out);
+        out
+    }
+
+    /// Restores a game state from the binary save format, checking
+    /// that the data is intact and describes a valid game
+    pub fn load(data: &[u8]) -> Result<Self, LoadError> {
+        let mut input = unseal(data, MAGIC, SAVE_VERSION)?;
+
+        let options = Options::read(&mut input)?;
+        let galaxy: Vec<Option<Entity>> = Vec::read(&mut input)?;
+        let logbook: Vec<Vec<u8>> = Vec::read(&mut input)?;
+        let last_entry = Vec::read(&mut input)?;
+        let page = usize::read(&mut input)?;
+        let position: (usize, usize, usize, usize) = Persist::read(&mut input)?;
+        let visited: Vec<(usize, usize, usize, usize)> = Persist::read(&mut input)?;
+        let player = Ship::read(&mut input)?;
+        let morale = u8::read(&mut input)?;
+        let damage = Systems::read(&mut input)?;
+        let mission = usize::read(&mut input)?;
+        let date = usize::read(&mut input)?;
+        let seed = u64::read(&mut input)?;
+        let macros = Macros::read(&mut input)?;
+        let aliases = Aliases::read(&mut input)?;
+
+        let mut key = [0u8; 32];
+        for b in key.iter_mut() {
+            *b = u8::read(&mut input)?;
+        }
+        let mut rng = ChaCha8Rng::from_seed(key);
+        rng.set_stream(u64::read(&mut input)?);
+        rng.set_word_pos(u128::read(&mut input)?);
+        let replay = Persist::read(&mut input)?;
+
+        let (sectors, systems) = (options.sectors, options.systems);
+        if !input.is_empty()
+            || galaxy.len() != sectors * sectors * systems * systems
+            || page >= logbook.len()
+            || morale > MORALE
+            || aliases.iter().any(|(name, _)| macros.get(name).is_some())
+        {
+            return Err(LoadError::Corrupt);
+        }
+
+        // Positions are only valid within the saved galaxy
+        let checked =
+            |(x, y, xx, yy)| Position::new(x, y, xx, yy, &options).map_err(|_| LoadError::Corrupt);
+        let position = checked(position)?;
+        let visited = visited.into_iter().map(checked).collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            galaxy,
+            logbook,
+            last_entry,
+            page,
+            position,
+            visited,
+            player,
+            morale,
+            systems: damage,
+            mission,
+            date,
+            seed,
+            options,
+            macros,
+            aliases,
+            rng,
+            replay,
+        })
+    }
+
+    /// Returns the player's current position in the galaxy
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the position of a sector in the player's current system
+    fn local(&self, sector: SectorCoord) -> Position {
+        Position {
+            sector,
+            ..self.position
         }
     }
 
+    /// Returns whatever occupies the given position
+    fn at(&self, position: Position) -> Option<Entity> {
+        self.galaxy[position.index(&self.options)]
+    }
+
+    /// Whether the thing at the position is hidden from the player: Remulins
+    /// stay cloaked until close enough to strike
+    fn cloaked(&self, position: Position, thing: Entity) -> bool {
+        match thing {
+            Entity::Remulins(ship) => {
+                position.system != self.position.system
+                    || self.position.sector.distance(position.sector) > ship.range as f64
+            }
+            _ => false,
+        }
+    }
+
+    /// Fills (or empties) the given position
+    fn place(&mut self, position: Position, entity: Option<Entity>) {
+        self.galaxy[position.index(&self.options)] = entity;
+    }
+
     /// Evolves the entities in the player's system by one time period
     fn evolve(&mut self, hostile: bool) {
         use Entity::*;
 
-        let mut rng = thread_rng();
         let mut done = HashSet::new();
-        let (x, y, xx, yy) = self.position;
-        for i in 0..SECTORS {
-            for j in 0..SECTORS {
-                let sector = index!(j, i, xx, yy);
-                if done.contains(&sector) {
-                    continue;
-                }
-                let dr = (y.abs_diff(i) as f64).hypot(x.abs_diff(j) as f64);
-
-                if let Some(
-                    thing @ (Klargons(ship) | Remulins(ship) | Faringa(ship) | Berg(ship)),
-                ) = self.galaxy[sector]
-                {
-                    // If this evolution is hostile, enemies within range
-                    // can attack player
-                    let ship = if hostile && (ship.range as f64) >= dr {
-                        let new;
-                        let beam = if rng.gen_bool(0.5) {
-                            let laser = rng.gen_range((ship.energy / 4)..(ship.energy / 2));
-                            new = Ship {
-                                energy: ship.energy - laser,
-                                ..ship
-                            };
-                            laser
-                        } else {
-                            let number = rng.gen_range(0..ship.torpedoes);
-                            new = Ship {
-                                torpedoes: ship.torpedoes - number,
-                                ..ship
-                            };
-                            50u8.saturating_mul(number)
-                        };
-                        let (damage, player) = self.fire(beam, self.player);
-                        self.player = player;
-
-                        self.record(bconcat!(
-                            b"\nEnemy ",
-                            thing,
-                            b" have attacked!\nWe've taken ",
-                            damage,
-                            b" damage.\nRemaining ENERGY:  ",
-                            self.player.energy,
-                            b"\n          SHIELDS: ",
-                            self.player.shields,
-                            b"\n"
-                        ));
-
-                        new
-                    } else {
-                        ship
-                    };
-
-                    // Otherwise try to move in a random direction closer
-                    // to the player (equivalent to speed 1)
-                    for (n, m) in adjacent(j, i) {
-                        let new = index!(n, m, xx, yy);
-                        if self.galaxy[new].is_none() && n != x && m != y {
-                            let ds = (y.abs_diff(m) as f64).hypot(x.abs_diff(n) as f64);
-                            if ds < dr && ship.energy > 1 {
-                                let ship = Ship {
-                                    energy: ship.energy - 1,
-                                    ..ship
-                                };
-                                self.galaxy[sector] = None;
-                                self.galaxy[new] = thing.update(ship);
-
-                                done.insert(new);
-
-                                self.record(bconcat!(
-                                    b"\nEnemy ",
-                                    thing,
-                                    b" moved to SECTOR: (",
-                                    n,
-                                    b",",
-                                    m,
-                                    b").\n"
-                                ));
-                                break;
-                            }
-                        }
-                    }
+        let player = self.position.sector;
+        for sector in SectorCoord::all(&self.options) {
+            if done.contains(&sector) {
+                continue;
+            }
+            let here = self.local(sector);
+            let dr = player.distance(sector);
+
+            if let Some(thing @ (Klargons(ship) | Remulins(ship) | Faringa(ship) | Berg(ship))) =
+                self.at(here)
+            {
+                // If this evolution is hostile, enemies within range
+                // can attack player
+                let engaged = hostile && (ship.range as f64) >= dr;
+                let ship = if engaged {
+                    let ship = self.attack(thing, ship);
+                    self.place(here, thing.update(ship));
+                    ship
+                } else {
+                    ship
+                };
+
+                // Then manoeuvre, falling back when low on energy (as do
+                // Faringa making off with what they have taken), and
+                // otherwise closing to the ship's preferred range
+                let retreat = ship.energy < ship.capacity.energy / 4
+                    || (engaged && matches!(thing, Faringa(_)));
+                if let Some((next, steps)) = self.manoeuvre(sector, ship, retreat) {
+                    let mut ship = ship;
+                    ship.drain(steps);
+                    self.place(here, None);
+                    self.place(self.local(next), thing.update(ship));
+
+                    done.insert(next);
+
+                    let verb: &[u8] = if retreat { b"retreated" } else { b"moved" };
+                    self.record(bconcat!(
+                        b"\nEnemy ",
+                        thing,
+                        b" ",
+                        verb,
+                        b" to SECTOR: ",
+                        next,
+                        b".\n"
+                    ));
                 }
+            }
 
-                // Small chance of star going supernova
-                if let Some(Star) = self.galaxy[sector] {
-                    if rng.gen_bool(0.01) {
-                        self.galaxy[sector] = None;
-
-                        // Damage player
-                        let (_, player) = self.fire(50, self.player);
-                        self.player = player;
-
-                        self.record(bconcat!(
-                            b"\nA nearby star went supernova!",
-                            b"\nRemaining ENERGY:  ",
-                            self.player.energy,
-                            b"\n          SHIELDS: ",
-                            self.player.shields,
-                            b"\n"
-                        ));
-
-                        // Damage other ships in the system
-                        for ii in 0..SECTORS {
-                            for jj in 0..SECTORS {
-                                let sector = index!(jj, ii, xx, yy);
-                                if let Some(
-                                    thing @ (Klargons(ship) | Remulins(ship) | Faringa(ship)
-                                    | Berg(ship)),
-                                ) = self.galaxy[sector]
-                                {
-                                    self.hit(sector, 50, thing, ship);
-                                }
-                            }
+            // Small chance of star going supernova
+            if let Some(Star) = self.at(here) {
+                if self.rng.gen_bool(0.01) {
+                    self.place(here, None);
+
+                    // Damage player
+                    self.player.absorb(50);
+
+                    self.record(bconcat!(
+                        b"\nA nearby star went supernova!",
+                        b"\nRemaining ENERGY:  ",
+                        self.player.energy,
+                        b"\n          SHIELDS: ",
+                        self.player.shields,
+                        b"\n"
+                    ));
+                    self.knock_out(50);
+
+                    // Damage other ships in the system
+                    for other in SectorCoord::all(&self.options) {
+                        let there = self.local(other);
+                        if let Some(
+                            thing @ (Klargons(ship) | Remulins(ship) | Faringa(ship) | Berg(ship)),
+                        ) = self.at(there)
+                        {
+                            self.hit(there, 50, thing, ship, None);
                         }
                     }
                 }
             }
         }
 
+        // The crew tire as the voyage wears on, but
+        // make repairs as they go
         self.date += 1;
+        self.systems.repair();
+        if self.date.is_multiple_of(5) {
+            self.lower_morale(1);
+        }
     }
 
-    /// Prints a helpful list of commands
-    fn help(&self, term: &mut Terminal) {
-        term.message(
-            b"HELP - print this list of commands
+    /// Plans an enemy ship's move through the system, returning the
+    /// sector it should reach and the steps taken, or nothing if it
+    /// would do best to stay put. The ship finds the best sector it can
+    /// reach, routing around anything in its way, and sets out towards
+    /// it as fast as it can without running out of energy
+    fn manoeuvre(
+        &self,
+        from: SectorCoord,
+        ship: Ship,
+        retreat: bool,
+    ) -> Option<(SectorCoord, u16)> {
+        let player = self.position.sector;
+        let speed = ship.speed.min(ship.energy.saturating_sub(1));
+        if speed == 0 {
+            return None;
+        }
 
-MOVE s x y [X Y] - move towards sector
-position (x, y) [optionally in system (X, Y)]
-at speed s, where s is between 1 and 10
+        // Black holes, stars and everything else block the way
+        let routes = Routes::search(from, &self.options, |s| {
+            s != player && self.at(self.local(s)).is_none()
+        });
+
+        // Retreating ships want to be as far away as possible, others
+        // as near as they can be to the edge of their range
+        let badness = |s: SectorCoord| {
+            let dr = player.distance(s);
+            if retreat {
+                -dr
+            } else {
+                (dr - ship.range as f64).abs()
+            }
+        };
+        let goal = routes.reachable().min_by(|&a, &b| {
+            badness(a)
+                .total_cmp(&badness(b))
+                .then(routes.steps(a).cmp(&routes.steps(b)))
+        })?;
+        if badness(goal) >= badness(from) {
+            return None;
+        }
 
-LASER e x y - fire lasers with energy e towards
-position (x, y)
+        let route = routes.route(goal)?;
+        let steps = route.len().min(speed as usize);
+        Some((route[steps - 1], steps as u16))
+    }
 
-TORPEDO t x y - fire t torpedoes towards
-position (x, y)
+    /// Makes an enemy's attack on the player, in the manner of its
+    /// faction, returning its ship as it is afterwards
+    fn attack(&mut self, enemy: Entity, ship: Ship) -> Ship {
+        use Entity::*;
+        let mut ship = ship;
 
-SHIELDS e - raise shields using energy e
+        // Faringa avoid a fight, instead siphoning off energy
+        if let Faringa(_) = enemy {
+            let stolen = roll(&mut self.rng, 0..(self.player.energy / 4));
+            let stolen = ship.charge(stolen);
+            self.player.drain(stolen);
 
-SCAN - perform a short range scan of the system
+            self.record(bconcat!(
+                b"\nEnemy ",
+                enemy,
+                b" have siphoned off ",
+                stolen,
+                b" energy!\nRemaining ENERGY:  ",
+                self.player.energy,
+                b"\n"
+            ));
+            return ship;
+        }
 
-SURVEY - perform a long range scan of the galaxy
+        let torpedoes = match enemy {
+            // Klargons fire off their torpedoes whenever they have any
+            Klargons(_) => ship.torpedoes > 0,
+            // Remulins rely on a surprise laser strike as they decloak
+            Remulins(_) => false,
+            _ => self.rng.gen_bool(0.5),
+        };
+        let beam = if torpedoes {
+            let number = match enemy {
+                Klargons(_) => roll(&mut self.rng, 1..(ship.torpedoes.min(3) + 1)),
+                _ => roll(&mut self.rng, 0..ship.torpedoes),
+            };
+            ship.torpedoes -= number;
+            50u16.saturating_mul(number)
+        } else {
+            let laser = match enemy {
+                Remulins(_) => roll(&mut self.rng, (ship.energy / 3)..(ship.energy / 3 * 2)),
+                _ => roll(&mut self.rng, (ship.energy / 4)..(ship.energy / 2)),
+            };
+            ship.drain(laser);
+            laser
+        };
 
-INVESTIGATE - search for energy supplies
+        let damage = self.player.absorb(beam);
+        // Every hit shakes the crew a little
+        self.lower_morale(1 + damage / 25);
 
-DOCK - dock your ship at a base to resupply
+        self.record(bconcat!(
+            b"\nEnemy ",
+            enemy,
+            b" have attacked!\nWe've taken ",
+            damage,
+            b" damage.\nRemaining ENERGY:  ",
+            self.player.energy,
+            b"\n          SHIELDS: ",
+            self.player.shields,
+            b"\nCrew MORALE: ",
+            self.morale,
+            b"\n"
+        ));
+        self.knock_out(damage);
 
-LOG n - print page n of the ship's log",
-        );
-        term.update_console();
+        ship
     }
 
-    /// Moves the player's ship as specified
-    fn movement(&mut self, args: &[usize], term: &mut Terminal) {
-        if let &[speed, x1, y1, ..] = args {
-            if speed > 0 {
-                let (x0, y0, xx0, yy0) = self.position;
-
-                // First we process the intra-system movement
-                let mut x = x0 as f64;
-                let mut y = y0 as f64;
-                loop {
-                    let dx = x1 as f64 - x;
-                    let dy = y1 as f64 - y;
-                    let dr = dx.hypot(dy);
-
-                    if self.player.energy == 0 || dr < 1e-2 {
-                        break;
-                    }
+    /// Gives a hit of the given damage a chance of knocking out
+    /// one of the player's subsystems, for longer the harder it is
+    fn knock_out(&mut self, damage: u16) {
+        if damage == 0 || !self.rng.gen_bool((damage as f64 / 100.0).min(0.75)) {
+            return;
+        }
+        let system = Subsystem::ALL[self.rng.gen_range(0..Subsystem::ALL.len())];
+        let time = 2 + self.rng.gen_range(0..=(damage / 25).min(10)) as u8;
+        self.systems.disable(system, time);
 
-                    if speed as f64 > dr {
-                        x = x1 as f64;
-                        y = y1 as f64;
-                        self.player.energy = self.player.energy.saturating_sub(dr.round() as u8);
-                    } else {
-                        x += (speed as f64 * dx / dr).round();
-                        y += (speed as f64 * dy / dr).round();
-                        self.player.energy = self.player.energy.saturating_sub(speed as u8);
-                    }
+        self.record(bconcat!(b"\n", system, b" damaged!\n"));
+    }
 
-                    self.record(bconcat!(
-                        b"\nMoved to SECTOR: (",
-                        x,
-                        b", ",
-                        y,
-                        b") in SYSTEM: (",
-                        xx0,
-                        b", ",
-                        yy0,
-                        b").\nRemaining ENERGY: ",
-                        self.player.energy,
-                        b"\n"
-                    ));
+    /// Raises the crew's morale, up to MORALE
+    fn lift_morale(&mut self, amount: u8) {
+        self.morale = self.morale.saturating_add(amount).min(MORALE);
+    }
 
-                    // Collisions damage shields or drain energy
-                    if let Some(e) = self.galaxy[index!(x as usize, y as usize, xx0, yy0)] {
-                        if e == Entity::BlackHole {
-                            self.record(
-                                b"\nYour ship fell into a black hole!
-The hull lost integrity under the intense
-gravitational pull and was crushed along
-with any remaining crew onboard.\n",
-                            );
-                            self.player.energy = 0;
-                            return;
-                        }
+    /// Lowers the crew's morale
+    fn lower_morale(&mut self, amount: u16) {
+        self.morale = u16::from(self.morale).saturating_sub(amount) as u8;
+    }
 
-                        let mut dmg = thread_rng().gen_range(0..DIFFICULTY);
-                        if self.player.shields > dmg {
-                            self.player.shields -= dmg;
-                        } else {
-                            dmg -= self.player.shields;
-                            self.player.shields = 0;
-                            self.player.energy = self.player.energy.saturating_sub(dmg);
-                        }
+    /// Whether a demoralised crew refuses an order, which becomes
+    /// likelier the lower their morale falls
+    fn refuses(&mut self) -> bool {
+        self.morale < MUTINY && self.rng.gen_bool((MUTINY - self.morale) as f64 / 50.0)
+    }
 
-                        self.record(bconcat!(
-                            b"\nCollided with: ",
-                            e,
-                            b"!\nRemaining ENERGY: ",
-                            self.player.energy,
-                            b"\nRemaining SHIELDS: ",
-                            self.player.shields,
-                            b"\n"
-                        ));
-                    }
+    /// Weakens a shot fired by a demoralised crew, who are
+    /// slower to react
+    fn aim(&self, beam: u16) -> u16 {
+        let morale = self.morale.min(MORALE / 2) as u32;
+        let aimed = u32::from(beam) * (MORALE as u32 / 2 + morale) / MORALE as u32;
+        u16::try_from(aimed).unwrap_or(u16::MAX)
+    }
 
-                    self.evolve(true);
-                }
+    /// Prints a helpful list of commands
+    fn help(&self, term: &mut Terminal) {
+        let mut out = Vec::new();
+        for spec in COMMANDS.iter().filter(|spec| !spec.help.is_empty()) {
+            out.extend_from_slice(bconcat!(spec.usage().as_slice(), b" - ", spec.help, b"\n"));
+        }
+        out.extend_from_slice(b"\nSeparate commands with ; to run them in turn.");
+        // Indent the rest of each entry to set it apart
+        term.message_indented(&out, 2);
+        term.update_console();
+    }
 
-                let mut xx = xx0 as f64;
-                let mut yy = yy0 as f64;
-                if let &[_, _, _, xx1, yy1] = args {
-                    // Finally we process the inter-system movement.
-                    // We do not check for collisions here, as inter-system
-                    // space is assumed to be sparse. We also do not evolve
-                    // the entities any further (to save a few cycles).
-                    loop {
-                        let dx = xx1 as f64 - xx;
-                        let dy = yy1 as f64 - yy;
-                        let dr = dx.hypot(dy);
-
-                        if self.player.energy == 0 || dr < 1e-2 {
-                            break;
-                        }
+    /// Moves the player's ship as specified
+    fn movement(
+        &mut self,
+        speed: usize,
+        (x, y): (usize, usize),
+        system: Option<(usize, usize)>,
+        term: &mut Terminal,
+    ) {
+        let target = match system {
+            None => SectorCoord::new(x, y, &self.options).map(|sector| self.local(sector)),
+            Some((xx, yy)) => Position::new(x, y, xx, yy, &self.options),
+        };
+        let target = match target {
+            Ok(target) => target,
+            Err(err) => {
+                term.message(bconcat!(err, b"\n\nRun SCAN or SURVEY to see the charts."));
+                term.update_console();
+                return;
+            }
+        };
 
-                        // It costs 10 times the energy to move between systems
-                        // compared to moving between sectors
-                        if speed as f64 > dr {
-                            xx = xx1 as f64;
-                            yy = yy1 as f64;
-                            self.player.energy =
-                                self.player.energy.saturating_sub((10.0 * dr.round()) as u8);
-                        } else {
-                            xx += (speed as f64 * dx / dr).round();
-                            yy += (speed as f64 * dy / dr).round();
-
-                            self.player.energy =
-                                self.player.energy.saturating_sub(10 * speed as u8);
-                        }
+        // Without the warp engines the ship can only crawl
+        // about the system on impulse power
+        let speed = if self.systems.working(Subsystem::Engines) {
+            speed.min(self.player.speed as usize)
+        } else if target.system != self.position.system {
+            self.inoperable(Subsystem::Engines, term);
+            return;
+        } else {
+            1
+        };
 
-                        self.record(bconcat!(
-                            b"\nMoved to SECTOR: (",
-                            x,
-                            b", ",
-                            y,
-                            b") in SYSTEM: (",
-                            xx,
-                            b", ",
-                            yy,
-                            b").\nRemaining ENERGY: ",
-                            self.player.energy,
-                            b"\n"
-                        ));
-                    }
+        // First we process the intra-system movement
+        while self.player.energy > 0 && self.position.sector != target.sector {
+            let (sector, dr) = self.position.sector.step_towards(target.sector, speed);
+            self.position.sector = sector;
+            self.player.drain(dr.round() as u16);
+
+            self.record(bconcat!(
+                b"\nMoved to SECTOR: ",
+                self.position.sector,
+                b" in SYSTEM: ",
+                self.position.system,
+                b".\nRemaining ENERGY: ",
+                self.player.energy,
+                b"\n"
+            ));
+
+            // Collisions damage shields or drain energy
+            if let Some(e) = self.at(self.position) {
+                if e == Entity::BlackHole {
+                    self.record(
+                        b"\nYour ship fell into a black hole! The hull lost integrity under \
+                        the intense gravitational pull and was crushed along with \
+                        any remaining crew onboard.\n",
+                    );
+                    self.player.energy = 0;
+                    return;
                 }
 
-                self.position = (x as usize, y as usize, xx as usize, yy as usize);
-                self.scan(term);
-                return;
+                let difficulty = self.options.difficulty.value();
+                let dmg = self.rng.gen_range(0..difficulty).into();
+                self.player.absorb(dmg);
+
+                self.record(bconcat!(
+                    b"\nCollided with: ",
+                    e,
+                    b"!\nRemaining ENERGY: ",
+                    self.player.energy,
+                    b"\nRemaining SHIELDS: ",
+                    self.player.shields,
+                    b"\n"
+                ));
+                self.knock_out(dmg);
             }
+
+            self.evolve(true);
         }
 
-        // Incorrect arguments
-        term.message(
-            b"MOVE requires at least three positive
-numeric arguments:
+        // Finally we process the inter-system movement.
+        // We do not check for collisions here, as inter-system
+        // space is assumed to be sparse. We also do not evolve
+        // the entities any further (to save a few cycles).
+        while self.player.energy > 0 && self.position.system != target.system {
+            let (system, dr) = self.position.system.step_towards(target.system, speed);
+            self.position.system = system;
 
-    MOVE s x y [X Y]
+            // It costs 10 times the energy to move between systems
+            // compared to moving between sectors, and wearies the crew
+            let jumps = dr.round() as u16;
+            self.player.drain(jumps.saturating_mul(10));
+            self.lower_morale(jumps);
 
-The speed s must be greater than zero.
+            self.record(bconcat!(
+                b"\nMoved to SECTOR: ",
+                self.position.sector,
+                b" in SYSTEM: ",
+                self.position.system,
+                b".\nRemaining ENERGY: ",
+                self.player.energy,
+                b"\n"
+            ));
+        }
 
-Run HELP for more commands.",
-        );
-        term.update_console();
+        self.scan(term);
     }
 
-    /// Calculates a hit on another ship
-    fn fire(&mut self, beam: u8, ship: Ship) -> (u8, Ship) {
-        let total = ship.energy + ship.shields;
-        let damage = if total > beam { beam } else { total };
-        let rem = if ship.shields > damage {
-            0
+    /// Calculates a hit on an enemy ship by the given weapon, if any,
+    /// and updates galaxy
+    fn hit(
+        &mut self,
+        target: Position,
+        beam: u16,
+        enemy: Entity,
+        ship: Ship,
+        weapon: Option<Weapon>,
+    ) -> (u16, bool) {
+        let mut ship = ship;
+
+        // Shields tuned against the weapon turn half of it aside,
+        // and the Berg tune theirs against whatever hits them
+        let beam = if weapon.is_some() && ship.tuned == weapon {
+            beam / 2
         } else {
-            damage - ship.shields
+            beam
         };
-
-        let shields = if rem > 0 { 0 } else { ship.shields - damage };
-        let energy = ship.energy.saturating_sub(rem);
-        let ship = Ship {
-            energy,
-            shields,
-            ..ship
-        };
-        (damage, ship)
-    }
-
-    /// Calculates a hit on an enemy ship and updates galaxy
-    fn hit(&mut self, sector: usize, beam: u8, enemy: Entity, ship: Ship) -> (u8, bool) {
-        let (damage, ship) = self.fire(beam, ship);
+        let damage = ship.absorb(beam);
+        if let (Entity::Berg(_), Some(_)) = (enemy, weapon) {
+            ship.tuned = weapon;
+        }
 
         let destroyed;
-        self.galaxy[sector] = if ship.energy > 0 {
+        let remains = if ship.energy > 0 {
             destroyed = false;
             enemy.update(ship)
         } else {
             self.record(bconcat!(b"\nEnemy ", enemy, b" destroyed!\n"));
             self.mission += 1;
+            self.lift_morale(5);
             destroyed = true;
             None
         };
+        self.place(target, remains);
 
         (damage, destroyed)
     }
 
     /// Fires the player's lasers
-    fn laser(&mut self, sector: usize, beam: u8, enemy: Entity, ship: Ship) -> Vec<u8> {
-        let (damage, destroyed) = self.hit(sector, beam, enemy, ship);
+    fn laser(&mut self, target: Position, beam: u16, enemy: Entity, ship: Ship) -> Vec<u8> {
+        let (damage, destroyed) =
+            self.hit(target, self.aim(beam), enemy, ship, Some(Weapon::Lasers));
 
         // Player loses all energy from beam (even if damage < beam)
-        self.player.energy = self.player.energy.saturating_sub(beam);
+        self.player.drain(beam);
 
-        let msg = if destroyed {
-            b"Their ship has been destroyed.\n"
-        } else {
-            b"                              \n"
-        };
+        let msg = aftermath(destroyed, ship, Weapon::Lasers);
 
         bconcat!(
             b"\nHit ",
@@ -414,40 +689,39 @@ Run HELP for more commands.",
             b" with lasers inflicting ",
             damage,
             b" damage!\n",
-            msg
+            msg.as_slice()
         )
         .to_vec()
     }
 
     /// Fires the player's torpedoes as specified
-    fn torpedo(&mut self, sector: usize, number: u8, enemy: Entity, ship: Ship) -> Vec<u8> {
-        let number = if number > self.player.torpedoes {
-            self.player.torpedoes
-        } else {
-            number
-        };
+    fn torpedo(&mut self, target: Position, number: u16, enemy: Entity, ship: Ship) -> Vec<u8> {
+        let number = number.min(self.player.torpedoes);
 
         // Torpedoes do 100 damage down-weighted by the difficulty setting
-        let beam = (number as f64) * 100.0 * (255.0 - DIFFICULTY as f64) / 255.0;
-        let (damage, destroyed) = self.hit(sector, beam as u8, enemy, ship);
+        let difficulty = self.options.difficulty.value() as f64;
+        let beam = (number as f64) * 100.0 * (255.0 - difficulty) / 255.0;
+        let (damage, destroyed) = self.hit(
+            target,
+            self.aim(beam as u16),
+            enemy,
+            ship,
+            Some(Weapon::Torpedoes),
+        );
 
-        self.player.torpedoes = self.player.torpedoes.saturating_sub(number);
+        self.player.torpedoes -= number;
 
-        let msg = if destroyed {
-            b"Their ship has been destroyed.\n"
-        } else {
-            b"                              \n"
-        };
+        let msg = aftermath(destroyed, ship, Weapon::Torpedoes);
 
         bconcat!(
             b"\nHit ",
             enemy,
             b" with ",
             number,
-            b" torpedo(es),\ninflicting ",
+            b" torpedo(es), inflicting ",
             damage,
             b" damage!\n",
-            msg
+            msg.as_slice()
         )
         .to_vec()
     }
@@ -455,85 +729,76 @@ Run HELP for more commands.",
     /// Fires the player's weapons as specified
     fn weapon(
         &mut self,
-        args: &[usize],
+        amount: usize,
+        (x, y): (usize, usize),
         term: &mut Terminal,
-        weapon: fn(&mut GameState, usize, u8, Entity, Ship) -> Vec<u8>,
-        name: &[u8],
+        weapon: fn(&mut GameState, Position, u16, Entity, Ship) -> Vec<u8>,
     ) {
         use Entity::*;
-        if let &[amount, x, y] = args {
-            if amount > 0 {
-                let (j, i, xx, yy) = self.position;
-                let sector = index!(x, y, xx, yy);
-                let r = (x.abs_diff(j) as f64).hypot(y.abs_diff(i) as f64) as u8;
-                let msg = if let Some(
-                    thing @ (Klargons(ship) | Remulins(ship) | Faringa(ship) | Berg(ship)),
-                ) = self.galaxy[sector]
-                {
-                    if self.player.range >= r {
-                        weapon(self, sector, amount as u8, thing, ship)
-                    } else {
-                        bconcat!(b"(", x, b", ", y, b") out of range!").to_vec()
-                    }
-                } else {
-                    bconcat!(b"Nothing to target at (", x, b", ", y, b")!").to_vec()
-                };
-
-                self.record(&msg);
-                term.message(&msg);
+        let sector = match SectorCoord::new(x, y, &self.options) {
+            Ok(sector) => sector,
+            Err(err) => {
+                term.message(bconcat!(
+                    err,
+                    b"\n\nWeapons can only target sectors in the current system."
+                ));
                 term.update_console();
-                self.evolve(true);
                 return;
             }
-        }
-
-        // Incorrect arguments
-        term.message(bconcat!(
-            name,
-            b" requires three positive
-numeric arguments:
-
-",
-            name,
-            b" n x y
+        };
 
-where n must be greater than zero.
+        let target = self.local(sector);
+        let r = self.position.sector.distance(sector) as u16;
+        let msg =
+            if let Some(thing @ (Klargons(ship) | Remulins(ship) | Faringa(ship) | Berg(ship))) =
+                self.at(target)
+            {
+                if self.player.range >= r {
+                    weapon(self, target, saturate(amount), thing, ship)
+                } else {
+                    bconcat!(sector, b" out of range!").to_vec()
+                }
+            } else {
+                bconcat!(b"Nothing to target at ", sector, b"!").to_vec()
+            };
 
-Run HELP for more commands."
-        ));
+        self.record(&msg);
+        term.message(&msg);
         term.update_console();
+        self.evolve(true);
     }
 
     /// Raises the player's shields as specified
-    fn shields(&mut self, args: &[usize], term: &mut Terminal) {
-        if let &[energy] = args {
-            let energy = energy as u8;
-            if self.player.shields < 255 && energy <= self.player.energy {
-                self.player.shields = self.player.shields.saturating_add(energy);
-                self.player.energy = self.player.energy.saturating_sub(energy);
-
-                let msg = bconcat!(
-                    b"\nEnergy diverted to shields:\nENERGY:  ",
-                    self.player.energy,
-                    b"\nSHIELDS: ",
-                    self.player.shields,
-                    b"\n"
-                );
-                term.message(msg);
-                self.record(msg);
-                term.update_console();
+    fn shields(&mut self, energy: usize, term: &mut Terminal) {
+        let energy = saturate(energy);
+        let full = self.player.shields >= self.player.capacity.shields;
+        if !full && energy <= self.player.energy {
+            self.player.divert(energy);
+
+            let msg = bconcat!(
+                b"\nEnergy diverted to shields:\nENERGY:  ",
+                self.player.energy,
+                b"\nSHIELDS: ",
+                self.player.shields,
+                b"\n"
+            );
+            term.message(msg);
+            self.record(msg);
+            term.update_console();
 
-                self.evolve(true);
-                return;
-            }
+            self.evolve(true);
+            return;
         }
 
-        // Incorrect arguments
-        term.message(
-            b"SHIELDS command requires one positive argument!
+        term.message(bconcat!(
+            b"Unable to raise shields!
 
-Cannot raise shields beyond 255 energy.",
-        );
+Cannot divert more than the ",
+            self.player.energy,
+            b" energy available, or raise shields beyond ",
+            self.player.capacity.shields,
+            b" energy."
+        ));
         term.update_console();
     }
 
@@ -551,82 +816,158 @@ Cannot raise shields beyond 255 energy.",
             (b"BERG", 0x06),
         ];
         let mut out = vec![];
-        let mut enemies: usize = 0;
-        let (x, y, xx, yy) = self.position;
-        out.extend_from_slice(b"\n\n    0 1 2 3 4 5 6 7 8 9");
+        let Position { sector, system } = self.position;
+        let sectors = self.options.sectors;
+
+        let enemies = SectorCoord::all(&self.options)
+            .map(|s| self.local(s))
+            .filter(|&p| match self.at(p) {
+                Some(n @ (Klargons(_) | Remulins(_) | Faringa(_) | Berg(_))) => !self.cloaked(p, n),
+                _ => false,
+            })
+            .count();
+
+        // Without the short range sensors nothing else can be seen
+        let sensors = self.systems.working(Subsystem::ShortRangeSensors);
+
+        // Large systems don't fit on screen, so we only chart
+        // the part of the system surrounding the player
+        let cols = view(sector.x(), sectors, VIEW);
+        let rows = view(sector.y(), sectors, VIEW);
+        if sectors > VIEW {
+            out.extend_from_slice(bconcat!(
+                b" CHARTING SECTORS (",
+                cols.start,
+                b"-",
+                cols.end - 1,
+                b", ",
+                rows.start,
+                b"-",
+                rows.end - 1,
+                b") OF ",
+                sectors,
+                b"x",
+                sectors
+            ));
+        }
+        out.extend_from_slice(b"\n\n   ");
+        for j in cols.clone() {
+            out.extend_from_slice(bconcat!(b" ", j % 10));
+        }
         out.extend_from_slice(b"        PLAYER:     ");
-        out.push(if self.player.energy > 127 { 0x01 } else { 0x02 });
-        for i in 0..SECTORS {
-            out.extend_from_slice(bconcat!(b"\n  ", i));
-            for j in 0..SECTORS {
+        let healthy = self.player.energy > self.player.capacity.energy / 2;
+        out.push(if healthy { 0x01 } else { 0x02 });
+        let key = |out: &mut Vec<u8>, (name, glyph): (&[u8], u8)| {
+            out.extend_from_slice(b"        ");
+            out.extend_from_slice(name);
+            out.extend_from_slice(b":");
+            out.resize(out.len() + 11 - name.len(), b' ');
+            out.push(glyph);
+        };
+        let charted: Vec<SectorCoord> = SectorCoord::all(&self.options)
+            .filter(|s| cols.contains(&s.x()) && rows.contains(&s.y()))
+            .collect();
+        for (r, row) in charted.chunks(cols.len()).enumerate() {
+            let i = row[0].y();
+            out.extend_from_slice(if i < 10 { b"\n  " } else { b"\n " });
+            out.extend_from_slice(bconcat!(i));
+            for &s in row {
                 out.push(b' ');
-                if j == x && i == y {
-                    out.push(if self.player.energy > 127 { 0x01 } else { 0x02 });
+                if s == sector {
+                    out.push(if healthy { 0x01 } else { 0x02 });
+                } else if !sensors {
+                    out.push(b'?');
                 } else {
-                    out.push(match self.galaxy[index!(j, i, xx, yy)] {
+                    out.push(match self.at(self.local(s)) {
                         None => 0xFA,
                         Some(BlackHole) => 0x07,
                         Some(Star) => 0x08,
                         Some(Planet) => 0x09,
                         Some(Base) => 0x0B,
-                        Some(Klargons(_)) => {
-                            enemies += 1;
-                            0x03
-                        }
-                        Some(Remulins(_)) => {
-                            enemies += 1;
-                            0x04
-                        }
-                        Some(Faringa(_)) => {
-                            enemies += 1;
-                            0x05
-                        }
-                        Some(Berg(_)) => {
-                            enemies += 1;
-                            0x06
-                        }
+                        Some(n) if self.cloaked(self.local(s), n) => 0xFA,
+                        Some(Klargons(_)) => 0x03,
+                        Some(Remulins(_)) => 0x04,
+                        Some(Faringa(_)) => 0x05,
+                        Some(Berg(_)) => 0x06,
                     });
                 }
             }
 
-            if i < legend.len() {
-                out.extend_from_slice(b"        ");
-                out.extend_from_slice(legend[i].0);
-                out.extend_from_slice(b":");
-                out.resize(out.len() + 11 - legend[i].0.len(), b' ');
-                out.push(legend[i].1);
+            if let Some(&entry) = legend.get(r) {
+                key(&mut out, entry);
             }
         }
 
+        // Small charts have fewer rows than the legend, so the rest
+        // of it carries on below them
+        for &entry in legend.iter().skip(rows.len()) {
+            out.push(b'\n');
+            out.resize(out.len() + 3 + 2 * cols.len(), b' ');
+            key(&mut out, entry);
+        }
+
         out.extend_from_slice(bconcat!(
-            b"\n\n\n SECTOR:    (",
-            x,
-            b", ",
-            y,
-            b")\n SYSTEM:    (",
-            xx,
-            b", ",
-            yy,
-            b")\n ENERGY:    ",
+            b"\n\n SECTOR:    ",
+            sector,
+            b"\n SYSTEM:    ",
+            system,
+            b"\n ENERGY:    ",
             self.player.energy,
+            b" / ",
+            self.player.capacity.energy,
             b"\n SHIELDS:   ",
             self.player.shields,
+            b" / ",
+            self.player.capacity.shields,
             b"\n TORPEDOES: ",
             self.player.torpedoes,
-            b"\n DATE:      ",
+            b" / ",
+            self.player.capacity.torpedoes,
+            b"\n MORALE:    ",
+            self.morale,
+            b"%\n DATE:      ",
             self.date,
             b"\n ENEMIES:   ",
-            enemies,
+            if sensors {
+                enemies.display_bytes()
+            } else {
+                b"?".to_vec()
+            }
+            .as_slice(),
             b"\n MISSION:   ",
-            self.mission
+            self.mission,
+            b" / ",
+            self.options.mission()
         ));
 
-        self.record(bconcat!(
-            b"\nScan completed: ",
-            enemies,
-            b" enemies detected in system!\n"
-        ));
-        term.message(&out);
+        if sensors {
+            self.record(bconcat!(
+                b"\nScan completed: ",
+                enemies,
+                b" enemies detected in system!\n"
+            ));
+        } else {
+            self.record(b"\nScan failed: SHORT RANGE SENSORS damaged!\n");
+        }
+
+        // Colour the chart so that threats stand out, and the
+        // player by the energy left
+        let third = self.player.capacity.energy / 3;
+        let player = match self.player.energy {
+            e if e > 2 * third => Attribute::fg(Colour::LightGreen),
+            e if e > third => Attribute::fg(Colour::Yellow),
+            _ => Attribute::fg(Colour::LightRed).blink(),
+        };
+        let styles: Vec<Attribute> = out
+            .iter()
+            .map(|&c| match c {
+                0x01 | 0x02 => player,
+                0x03..=0x06 => Attribute::fg(Colour::LightRed),
+                0x0B => Attribute::fg(Colour::LightGreen),
+                _ => Attribute::DEFAULT,
+            })
+            .collect();
+        term.message_styled(&out, &styles);
         term.update_console();
     }
 
@@ -634,27 +975,69 @@ Cannot raise shields beyond 255 energy.",
     fn survey(&self, term: &mut Terminal) {
         use Entity::*;
         let mut out = vec![];
-        let (_, _, xx, yy) = self.position;
-        out.extend_from_slice(b"\n\n     0   1   2   3   4   5   6   7   8   9");
-        for i in 0..SYSTEMS {
-            out.extend_from_slice(bconcat!(b"\n  ", i));
-            for j in 0..SYSTEMS {
+        let current = self.position.system;
+        let systems = self.options.systems;
+
+        // Larger systems can hold more than 9 of each kind of thing, so
+        // the totals are widened to fit as many as there could be
+        let most = 9 * self.options.sectors * self.options.sectors / (SECTORS * SECTORS);
+        let digits = most.max(1).ilog10() as usize + 1;
+        let width = 1 + 3 * digits;
+
+        // Large galaxies don't fit on screen, so we only chart
+        // the part of the galaxy surrounding the player
+        let shown = VIEW.min((WIDTH - 3) / width);
+        let cols = view(current.x(), systems, shown);
+        let rows = view(current.y(), systems, shown);
+        if systems > shown {
+            out.extend_from_slice(bconcat!(
+                b" CHARTING SYSTEMS (",
+                cols.start,
+                b"-",
+                cols.end - 1,
+                b", ",
+                rows.start,
+                b"-",
+                rows.end - 1,
+                b") OF ",
+                systems,
+                b"x",
+                systems
+            ));
+        }
+        out.extend_from_slice(b"\n\n   ");
+        for j in cols.clone() {
+            // Centre each number over its column
+            let label = j.display_bytes();
+            let left = (width - label.len()).div_ceil(2);
+            out.resize(out.len() + left, b' ');
+            out.extend_from_slice(&label);
+            out.resize(out.len() + width - left - label.len(), b' ');
+        }
+        let charted: Vec<SystemCoord> = SystemCoord::all(&self.options)
+            .filter(|s| cols.contains(&s.x()) && rows.contains(&s.y()))
+            .collect();
+        for row in charted.chunks(cols.len()) {
+            let i = row[0].y();
+            out.extend_from_slice(if i < 10 { b"\n  " } else { b"\n " });
+            out.extend_from_slice(bconcat!(i));
+            for s in row {
                 out.push(b' ');
 
                 // Scan adjacent systems
-                if (j + 1 >= xx && j <= xx + 1) && (i + 1 >= yy && i <= yy + 1) {
-                    let start = index!(0, 0, j, i);
-                    let system = &self.galaxy[start..(start + SECTORS * SECTORS)];
+                if s.x().abs_diff(current.x()) <= 1 && s.y().abs_diff(current.y()) <= 1 {
+                    let system = &self.galaxy[s.cells(&self.options)];
 
-                    let enemies: u8 = system
+                    let enemies: usize = system
                         .iter()
-                        .flatten()
-                        .map(|n| match n {
-                            Klargons(_) | Remulins(_) | Faringa(_) | Berg(_) => 1,
+                        .zip(SectorCoord::all(&self.options))
+                        .map(|(n, sector)| match n {
+                            Some(n) if self.cloaked(Position { sector, system: *s }, *n) => 0,
+                            Some(Klargons(_) | Remulins(_) | Faringa(_) | Berg(_)) => 1,
                             _ => 0,
                         })
                         .sum();
-                    let bases: u8 = system
+                    let bases: usize = system
                         .iter()
                         .flatten()
                         .map(|n| match n {
@@ -662,7 +1045,7 @@ Cannot raise shields beyond 255 energy.",
                             _ => 0,
                         })
                         .sum();
-                    let stars: u8 = system
+                    let stars: usize = system
                         .iter()
                         .flatten()
                         .map(|n| match n {
@@ -671,17 +1054,45 @@ Cannot raise shields beyond 255 energy.",
                         })
                         .sum();
 
-                    // There shouldn't be more than 9 of any of these
-                    out.extend_from_slice(bconcat!(enemies, bases, stars));
+                    for count in [enemies, bases, stars] {
+                        let count = count.display_bytes();
+                        out.resize(out.len() + digits.saturating_sub(count.len()), b'0');
+                        out.extend_from_slice(&count);
+                    }
                 } else {
-                    out.extend_from_slice(b"***");
+                    out.resize(out.len() + width - 1, b'*');
                 }
             }
         }
-        out.extend_from_slice(
-            b"\n\n\n    XYZ (SYSTEM TOTALS)\n    |||\n    \
-                                ||+-> STARS\n    |+--> BASES\n    +---> ENEMIES\n",
-        );
+
+        // The key points out where each total is found
+        out.extend_from_slice(b"\n\n\n    ");
+        for key in [b'X', b'Y', b'Z'] {
+            out.resize(out.len() + digits, key);
+        }
+        out.extend_from_slice(b" (SYSTEM TOTALS)\n    ");
+        let starts = [0, digits, 2 * digits];
+        for p in 0..=starts[2] {
+            out.push(if starts.contains(&p) { b'|' } else { b' ' });
+        }
+        for (&start, label) in starts
+            .iter()
+            .zip([&b"ENEMIES"[..], b"BASES", b"STARS"])
+            .rev()
+        {
+            out.extend_from_slice(b"\n    ");
+            for p in 0..(starts[2] + 3) {
+                out.push(match p {
+                    _ if p == start => b'+',
+                    _ if p > start && p < starts[2] + 2 => b'-',
+                    _ if p > start => b'>',
+                    _ if starts.contains(&p) => b'|',
+                    _ => b' ',
+                });
+            }
+            out.extend_from_slice(bconcat!(b" ", label));
+        }
+        out.push(b'\n');
 
         term.message(&out);
         term.update_console();
@@ -689,27 +1100,38 @@ Cannot raise shields beyond 255 energy.",
 
     /// Investigates adjacent stars or planets
     fn investigate(&mut self, term: &mut Terminal) {
-        if let Some((x, y, thing)) = nearby!(self, Planet, Star, BlackHole) {
-            let energy = thread_rng().gen_range(0..DIFFICULTY);
+        if let Some((sector, thing)) = nearby!(self, Planet, Star, BlackHole) {
+            let difficulty = self.options.difficulty.value();
+            let energy = self.rng.gen_range(0..difficulty);
+
+            // The crew most enjoy exploring planets
+            let (lift, spirits): (u8, &[u8]) = match thing {
+                Entity::Planet => (15, b"\nThe crew's spirits are lifted!"),
+                Entity::Star => (3, b""),
+                _ => (0, b""),
+            };
+            self.lift_morale(lift);
+
             term.message(bconcat!(
                 b"Investigated nearby ",
                 thing,
                 b".\nDiscovered ",
                 energy,
-                b" energy crystals!"
+                b" energy crystals!",
+                spirits
             ));
             self.record(bconcat!(
                 b"\nInvestigated nearby ",
                 thing,
-                b".\nAt SECTOR (",
-                x,
-                b", ",
-                y,
-                b").\nDiscovered ",
+                b".\nAt SECTOR ",
+                sector,
+                b".\nDiscovered ",
                 energy,
-                b" energy crystals!\n"
+                b" energy crystals!",
+                spirits,
+                b"\n"
             ));
-            self.player.energy = self.player.energy.saturating_add(energy);
+            self.player.charge(energy.into());
         } else {
             term.message(b"Nothing interesting nearby, unable to investigate!");
         }
@@ -719,22 +1141,20 @@ Cannot raise shields beyond 255 energy.",
 
     /// Docks the player's ship at an adjacent starbase
     fn dock(&mut self, term: &mut Terminal) {
-        if let Some((x, y, _)) = nearby!(self, Base) {
+        if let Some((sector, _)) = nearby!(self, Base) {
             term.message(
-                b"Docked with nearby base.
-Energy and shields restored!
-Protected from hostiles until next move.",
+                b"Docked with nearby base. Energy and shields restored, repairs \
+                completed! The crew enjoyed some shore leave. Protected from hostiles \
+                until next move.",
             );
             self.record(bconcat!(
-                b"\nDocked with base in SECTOR: (",
-                x,
-                b", ",
-                y,
-                b").\nEnergy, torpedoes and shields restored!\n"
+                b"\nDocked with base in SECTOR: ",
+                sector,
+                b".\nEnergy, torpedoes and shields restored!\n"
             ));
-            self.player.shields = 255;
-            self.player.energy = 255;
-            self.player.torpedoes = 7;
+            self.systems.repair_all();
+            self.player.refit();
+            self.lift_morale(30);
             self.evolve(false);
         } else {
             term.message(b"No bases nearby, unable to dock!");
@@ -743,10 +1163,42 @@ Protected from hostiles until next move.",
         term.update_console();
     }
 
+    /// Reports the state of repair of the player's subsystems
+    fn damage(&self, term: &mut Terminal) {
+        let mut out = b"DAMAGE REPORT\n\n".to_vec();
+        for system in Subsystem::ALL {
+            let name = system.display_bytes();
+            out.extend_from_slice(bconcat!(b" ", name.as_slice(), b":"));
+            out.resize(out.len() + 20 - name.len(), b' ');
+            match self.systems.repair_time(system) {
+                0 => out.extend_from_slice(b"OPERATIONAL\n"),
+                1 => out.extend_from_slice(b"DAMAGED (1 day)\n"),
+                time => out.extend_from_slice(bconcat!(b"DAMAGED (", time, b" days)\n")),
+            }
+        }
+        if self.systems.damaged() {
+            out.extend_from_slice(
+                b"\nRepairs are made as time passes, or all at once when docked at a base.",
+            );
+        }
+        term.message(&out);
+        term.update_console();
+    }
+
+    /// Explains that a command needs a subsystem that is damaged
+    fn inoperable(&self, system: Subsystem, term: &mut Terminal) {
+        term.message(bconcat!(
+            b"Unable to comply, ",
+            system,
+            b" damaged!\n\nSee the DAMAGE report for how long repairs will take."
+        ));
+        term.update_console();
+    }
+
     /// Displays a page of the log
-    fn log(&self, args: &[usize], term: &mut Terminal) {
-        match args {
-            [] => term.message(bconcat!(
+    fn log(&self, page: Option<usize>, term: &mut Terminal) {
+        match page {
+            None => term.message(bconcat!(
                 b"Captain's Log [",
                 self.page + 1,
                 b" / ",
@@ -754,19 +1206,143 @@ Protected from hostiles until next move.",
                 b"]\n\n",
                 self.logbook[self.page].as_slice()
             )),
-            [i] if *i < self.logbook.len() + 1 && *i > 0 => term.message(bconcat!(
+            Some(i) if i < self.logbook.len() + 1 && i > 0 => term.message(bconcat!(
                 b"Captain's Log [",
                 i,
                 b" / ",
                 self.logbook.len(),
                 b"]\n\n",
-                self.logbook[*i - 1].as_slice()
+                self.logbook[i - 1].as_slice()
             )),
             _ => term.message(b"Log page not found!"),
         };
         term.update_console();
     }
 
+    /// Defines, redefines or deletes a macro
+    fn define(&mut self, name: &[u8], body: &[u8], term: &mut Terminal) {
+        if !body.is_empty() && self.aliases.get(name).is_some() {
+            self.name_taken(name, b"DEFINE", term);
+            return;
+        }
+        let msg = match self.macros.get(name) {
+            None if body.is_empty() => bconcat!(b"No macro named ", name, b" to delete!").to_vec(),
+            _ if body.is_empty() => bconcat!(b"Deleted macro ", name, b".").to_vec(),
+            _ => bconcat!(
+                b"Defined macro ",
+                name,
+                b" as:\n\n    ",
+                body,
+                b"\n\nEnter ",
+                name,
+                b" to run it."
+            )
+            .to_vec(),
+        };
+        self.macros.define(name, body);
+        term.message(&msg);
+        term.update_console();
+    }
+
+    /// Prints the defined macros
+    fn list_macros(&self, term: &mut Terminal) {
+        if self.macros.is_empty() {
+            term.message(
+                b"No macros defined. For example:
+
+    DEFINE ALPHA = SH 50; L 40 3 4
+
+defines ALPHA to raise shields then fire lasers.",
+            );
+        } else {
+            let mut out = b"Macros:\n".to_vec();
+            for (name, body) in self.macros.iter() {
+                out.extend_from_slice(bconcat!(b"\n    ", name, b" = ", body));
+            }
+            term.message(&out);
+        }
+        term.update_console();
+    }
+
+    /// Lets the name stand for a built-in command
+    fn alias(&mut self, name: &[u8], command: &'static CommandSpec, term: &mut Terminal) {
+        if self.macros.get(name).is_some() {
+            self.name_taken(name, b"ALIAS", term);
+            return;
+        }
+        let msg = bconcat!(
+            name,
+            b" now stands for ",
+            command.name,
+            b".\n\nEnter ",
+            name,
+            b" where you would enter ",
+            command.name,
+            b", e.g.\n\n    ",
+            name,
+            &command.usage()[command.name.len()..]
+        )
+        .to_vec();
+        self.aliases.define(name, command);
+        term.message(&msg);
+        term.update_console();
+    }
+
+    /// Explains that a name is already used by a macro or alias.
+    /// Parsing catches most clashes, but not those with names
+    /// given earlier in the same line or by a macro
+    fn name_taken(&self, name: &[u8], by: &[u8], term: &mut Terminal) {
+        let err = ParseError {
+            kind: ParseErrorKind::Taken(name.to_ascii_uppercase()),
+            span: 0..0,
+            spec: CommandSpec::find(by),
+        };
+        term.message(&err.display_bytes());
+        term.update_console();
+    }
+
+    /// Removes an alias
+    fn unalias(&mut self, name: &[u8], term: &mut Terminal) {
+        if self.aliases.remove(name) {
+            term.message(bconcat!(b"Removed alias ", name, b"."));
+        } else {
+            term.message(bconcat!(b"No alias named ", name, b" to remove!"));
+        }
+        term.update_console();
+    }
+
+    /// Prints the aliases given by the captain
+    fn list_aliases(&self, term: &mut Terminal) {
+        if self.aliases.is_empty() {
+            term.message(
+                b"No aliases defined. For example:
+
+    ALIAS W MOVE
+
+lets you enter W 3 4 4 to MOVE 3 4 4.",
+            );
+        } else {
+            let mut out = b"Aliases:\n".to_vec();
+            for (name, spec) in self.aliases.iter() {
+                out.extend_from_slice(bconcat!(b"\n    ", name, b" = ", spec.name));
+            }
+            term.message(&out);
+        }
+        term.update_console();
+    }
+
+    /// Explains that the crew refused an order, which wastes a turn
+    fn mutiny(&mut self, term: &mut Terminal) {
+        term.message(
+            b"The crew refused your order!
+
+Their morale is too low. Investigate planets or dock at a base to lift their spirits.",
+        );
+        self.record(b"\nThe crew refused an order!\n");
+        self.evolve(true);
+        term.update_console();
+    }
+
     /// Writes an entry to the log
     fn record(&mut self, entry: &[u8]) {
         self.last_entry = entry.to_vec();
@@ -794,60 +1370,62 @@ Protected from hostiles until next move.",
         let mission = 5 * self.mission.pow(2);
         let energy = 4 * (self.player.energy as usize);
         let shields = 3 * (self.player.shields as usize);
-        mission + date + energy + shields
+        let morale = 2 * (self.morale as usize);
+        mission + date + energy + shields + morale
     }
 
-    /// Parses user input and dispatches to relevant methods
-    pub fn process_command(&mut self, command: &[u8], term: &mut Terminal) -> u8 {
-        // Split words on whitespace
-        let words: Vec<&[u8]> = command
-            .split(|c| c.is_ascii_whitespace())
-            .filter(|s| !s.is_empty())
-            .collect();
-        if words.is_empty() {
-            return 0;
+    /// Parses a line of user input into the commands it stands for
+    pub fn parse(&self, line: &[u8]) -> Result<Vec<Command>, ParseError> {
+        parse_line(line, &self.macros, &self.aliases)
+    }
+
+    /// Names of the commands, macros and aliases starting with the given text
+    pub fn completions(&self, prefix: &[u8]) -> Vec<Vec<u8>> {
+        completions(prefix, &self.macros, &self.aliases)
+    }
+
+    /// The arguments still to be given to the last command in the line
+    pub fn hint(&self, line: &[u8]) -> Vec<u8> {
+        hint(line, &self.aliases)
+    }
+
+    /// Parses user input and dispatches to relevant methods.
+    /// Commands separated by `;` are run in turn until the game ends
+    pub fn process_command(&mut self, line: &[u8], term: &mut Terminal) -> u8 {
+        let commands = self.parse(line);
+        if let Ok(ref commands) = commands {
+            if commands.is_empty() {
+                return 0;
+            }
         }
 
-        // Process any numeric arguments.
-        // Note: this will quietly ignore any non-numeric arguments
-        // that are interspersed with the numeric arguments
-        let args: Vec<usize> = words
-            .iter()
-            .flat_map(|n| String::from_utf8_lossy(n).parse::<usize>())
-            .collect();
+        if let Some(replay) = &mut self.replay {
+            replay.push(line);
+        }
 
-        // Dispatch based on command
-        match String::from_utf8_lossy(words[0])
-            .to_ascii_lowercase()
-            .as_str()
-        {
-            "help" | "h" => self.help(term),
-            "move" | "m" => self.movement(&args, term),
-            "laser" | "l" => self.weapon(&args, term, GameState::laser, b"LASER"),
-            "torpedo" | "t" => self.weapon(&args, term, GameState::torpedo, b"TORPEDO"),
-            "shields" | "sh" => self.shields(&args, term),
-            "scan" | "sc" => self.scan(term),
-            "survey" | "su" => self.survey(term),
-            "investigate" | "i" => self.investigate(term),
-            "dock" | "d" => self.dock(term),
-            "log" => self.log(&args, term),
-            "quit" | "q" => {
-                self.player.energy = 0;
+        match commands {
+            Ok(commands) => {
+                for command in commands {
+                    self.execute(command, term);
+                    if self.status() != 0 {
+                        break;
+                    }
+                }
             }
-            _ => {
-                term.message(bconcat!(
-                    b"Unrecognised command:\n\n    '",
-                    words[0],
-                    b"'\n\nTry the HELP command for a list of possible\ncommands!"
-                ));
+            Err(err) => {
+                term.message(&err.explain(line));
                 term.update_console();
             }
-        };
+        }
+
+        self.status()
+    }
 
-        // Player wins if mission is completed,
-        // loses if all energy is drained,
-        // otherwise game continues
-        if self.mission == MISSION {
+    /// Player wins if mission is completed (1),
+    /// loses if all energy is drained (2),
+    /// otherwise game continues (0)
+    fn status(&self) -> u8 {
+        if self.mission >= self.options.mission() {
             1
         } else if self.player.energy == 0 {
             2
@@ -855,80 +1433,135 @@ Protected from hostiles until next move.",
             0
         }
     }
+
+    /// Dispatches a command to the relevant method
+    fn execute(&mut self, command: Command, term: &mut Terminal) {
+        use Command::*;
+
+        // Commands needing a damaged subsystem cannot be carried out
+        let needs = match command {
+            Laser { .. } => Some(Subsystem::Lasers),
+            Torpedo { .. } => Some(Subsystem::Torpedoes),
+            Shields { .. } => Some(Subsystem::ShieldGenerator),
+            Survey => Some(Subsystem::LongRangeSensors),
+            _ => None,
+        };
+        if let Some(system) = needs.filter(|&s| !self.systems.working(s)) {
+            self.inoperable(system, term);
+            return;
+        }
+
+        // Orders given to a demoralised crew may not be carried out
+        let order = matches!(
+            command,
+            Move { .. } | Laser { .. } | Torpedo { .. } | Shields { .. } | Investigate | Dock
+        );
+        if order && self.refuses() {
+            self.mutiny(term);
+            return;
+        }
+
+        match command {
+            Help => self.help(term),
+            Move {
+                speed,
+                x,
+                y,
+                system,
+            } => self.movement(speed, (x, y), system, term),
+            Laser { energy, x, y } => self.weapon(energy, (x, y), term, GameState::laser),
+            Torpedo { number, x, y } => self.weapon(number, (x, y), term, GameState::torpedo),
+            Shields { energy } => self.shields(energy, term),
+            Scan => self.scan(term),
+            Survey => self.survey(term),
+            Investigate => self.investigate(term),
+            Dock => self.dock(term),
+            Damage => self.damage(term),
+            Log { page } => self.log(page, term),
+            Define { name, body } => self.define(&name, &body, term),
+            Macros => self.list_macros(term),
+            Alias { name, command } => self.alias(&name, command, term),
+            Unalias { name } => self.unalias(&name, term),
+            Aliases => self.list_aliases(term),
+            Quit => self.player.energy = 0,
+        }
+    }
 }
 
 /// Randomly generate a galaxy full of enemies and other entities
-fn generate_galaxy() -> [Option<Entity>; SYSTEMS * SYSTEMS * SECTORS * SECTORS] {
+fn generate_galaxy<R: Rng>(rng: &mut R, options: Options) -> Vec<Option<Entity>> {
     use Entity::*;
 
-    let mut galaxy = [None; SYSTEMS * SYSTEMS * SECTORS * SECTORS];
-    let difficulty = ((DIFFICULTY as f64 / 255.0) - 1.0).exp();
-
-    let mut rng = thread_rng();
-    for i in 0..SYSTEMS {
-        for j in 0..SYSTEMS {
-            let mut enemies = 0;
-            let mut others = 0;
-            let emax = (9.0 * rng.gen::<f64>() * difficulty) as usize;
-            let omax = (9.0 * rng.gen::<f64>() * difficulty) as usize;
-
-            for y in 0..SECTORS {
-                for x in 0..SECTORS {
-                    if rng.gen_bool(1.0 / 5.0) {
-                        galaxy[index!(x, y, j, i)] = match rng.gen() {
-                            e @ (BlackHole | Star | Planet | Base) if others < omax => {
-                                others += 1;
-                                Some(e)
-                            }
-                            e @ (Klargons(_) | Remulins(_) | Faringa(_) | Berg(_))
-                                if enemies < emax =>
-                            {
-                                enemies += 1;
-                                Some(e)
-                            }
-                            _ => None,
-                        };
+    let (sectors, systems, difficulty) = (options.sectors, options.systems, options.difficulty);
+    let mut galaxy = vec![None; sectors * sectors * systems * systems];
+    let density = ((difficulty.value() as f64 / 255.0) - 1.0).exp();
+
+    // Larger systems hold proportionally more entities
+    let density = density * (sectors * sectors) as f64 / (SECTORS * SECTORS) as f64;
+
+    for system in SystemCoord::all(&options) {
+        let mut enemies = 0;
+        let mut others = 0;
+        let emax = (9.0 * rng.gen::<f64>() * density) as usize;
+        let omax = (9.0 * rng.gen::<f64>() * density) as usize;
+
+        for sector in SectorCoord::all(&options) {
+            if rng.gen_bool(1.0 / 5.0) {
+                galaxy[Position { sector, system }.index(&options)] = match rng.sample(difficulty) {
+                    e @ (BlackHole | Star | Planet | Base) if others < omax => {
+                        others += 1;
+                        Some(e)
                     }
-                }
+                    e @ (Klargons(_) | Remulins(_) | Faringa(_) | Berg(_)) if enemies < emax => {
+                        enemies += 1;
+                        Some(e)
+                    }
+                    _ => None,
+                };
             }
-
-            // We've filled in row-major order, which will bias where we've
-            // placed our enemies. To fix that we shuffle the entire system
-            let start = index!(0, 0, j, i);
-            galaxy[start..(start + SECTORS * SECTORS)].shuffle(&mut rng);
         }
+
+        // We've filled in row-major order, which will bias where we've
+        // placed our enemies. To fix that we shuffle the entire system
+        galaxy[system.cells(&options)].shuffle(rng);
     }
     galaxy
 }
 
-/// Iterator over adjacent sector positions
-struct Adjacent {
-    coords: Vec<(usize, usize)>,
+/// Describes what became of an enemy ship hit by the weapon,
+/// given the ship as it was before
+fn aftermath(destroyed: bool, ship: Ship, weapon: Weapon) -> Vec<u8> {
+    if destroyed {
+        b"Their ship has been destroyed.\n".to_vec()
+    } else if ship.tuned == Some(weapon) {
+        bconcat!(b"Their shields were tuned against our ", weapon, b"!\n").to_vec()
+    } else {
+        b"                              \n".to_vec()
+    }
 }
 
-/// Create a new Adjacent iterator for the given coordinate
-fn adjacent(x: usize, y: usize) -> Adjacent {
-    let xmin = x.saturating_sub(1);
-    let ymin = y.saturating_sub(1);
-    let xmax = if x + 3 >= SECTORS { SECTORS } else { x + 3 };
-    let ymax = if y + 3 >= SECTORS { SECTORS } else { y + 3 };
-
-    let mut coords = Vec::new();
-    for i in xmin..xmax {
-        for j in ymin..ymax {
-            if (i, j) != (x, y) {
-                coords.push((i, j));
-            }
-        }
+/// Picks a value from the range, or its start if the range is empty
+/// (such as for an enemy with no torpedoes left)
+fn roll<R: Rng>(rng: &mut R, range: std::ops::Range<u16>) -> u16 {
+    if range.is_empty() {
+        range.start
+    } else {
+        rng.gen_range(range)
     }
-
-    Adjacent { coords }
 }
 
-impl Iterator for Adjacent {
-    type Item = (usize, usize);
+/// Converts a user-supplied amount to a ship stat, limiting
+/// rather than wrapping amounts too large to represent
+fn saturate(amount: usize) -> u16 {
+    u16::try_from(amount).unwrap_or(u16::MAX)
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.coords.pop()
+/// The range of at most `shown` co-ordinates to chart along a side
+/// of the given size, keeping the given co-ordinate in view
+fn view(centre: usize, size: usize, shown: usize) -> std::ops::Range<usize> {
+    if size <= shown {
+        return 0..size;
     }
+    let start = centre.saturating_sub(shown / 2).min(size - shown);
+    start..(start + shown)
 }
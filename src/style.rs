@@ -0,0 +1,128 @@
+/// Colours of the display, those of the 16-colour CGA palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Colour {
+    Black,
+    Blue,
+    Green,
+    Cyan,
+    Red,
+    Magenta,
+    Brown,
+    LightGrey,
+    DarkGrey,
+    LightBlue,
+    LightGreen,
+    LightCyan,
+    LightRed,
+    LightMagenta,
+    Yellow,
+    White,
+}
+
+impl Colour {
+    /// All the colours, in palette order
+    pub const ALL: [Colour; 16] = {
+        use Colour::*;
+        [
+            Black,
+            Blue,
+            Green,
+            Cyan,
+            Red,
+            Magenta,
+            Brown,
+            LightGrey,
+            DarkGrey,
+            LightBlue,
+            LightGreen,
+            LightCyan,
+            LightRed,
+            LightMagenta,
+            Yellow,
+            White,
+        ]
+    };
+
+    /// The brighter version of the colour, if there is one
+    pub fn bright(self) -> Colour {
+        Self::ALL[self as usize | 8]
+    }
+}
+
+/// How a tile of the display is drawn: its colours and any effects.
+/// Packed into 16 bits, with the foreground colour in bits 0-3, the
+/// background in bits 4-7, and then the bold, blink and inverse flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Attribute(u16);
+
+impl Attribute {
+    const BOLD: u16 = 1 << 8;
+    const BLINK: u16 = 1 << 9;
+    const INVERSE: u16 = 1 << 10;
+
+    /// White on black, as the tiles are drawn
+    pub const DEFAULT: Attribute = Attribute::new(Colour::White, Colour::Black);
+
+    /// Draws the tile in the given colours
+    pub const fn new(foreground: Colour, background: Colour) -> Self {
+        Self(foreground as u16 | (background as u16) << 4)
+    }
+
+    /// Draws the tile in the given colour on black
+    pub const fn fg(foreground: Colour) -> Self {
+        Self::new(foreground, Colour::Black)
+    }
+
+    /// Draws the tile bold, in the brighter version of its colour
+    pub const fn bold(self) -> Self {
+        Self(self.0 | Self::BOLD)
+    }
+
+    /// Makes the tile blink
+    pub const fn blink(self) -> Self {
+        Self(self.0 | Self::BLINK)
+    }
+
+    /// Swaps the foreground and background colours
+    pub const fn inverse(self) -> Self {
+        Self(self.0 | Self::INVERSE)
+    }
+
+    /// The colour the tile is drawn in
+    pub fn foreground(self) -> Colour {
+        Colour::ALL[(self.0 & 0xF) as usize]
+    }
+
+    /// The colour behind the tile
+    pub fn background(self) -> Colour {
+        Colour::ALL[(self.0 >> 4 & 0xF) as usize]
+    }
+
+    /// Whether the tile is drawn bold
+    pub fn is_bold(self) -> bool {
+        self.0 & Self::BOLD != 0
+    }
+
+    /// Whether the tile blinks
+    pub fn is_blink(self) -> bool {
+        self.0 & Self::BLINK != 0
+    }
+
+    /// Whether the foreground and background colours are swapped
+    pub fn is_inverse(self) -> bool {
+        self.0 & Self::INVERSE != 0
+    }
+
+    /// The packed representation, as read by frontends
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+}
+
+impl Default for Attribute {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
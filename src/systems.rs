@@ -0,0 +1,93 @@
+use crate::DisplayBytes;
+
+/// Parts of the player's ship that can be knocked out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Engines,
+    Lasers,
+    Torpedoes,
+    ShortRangeSensors,
+    LongRangeSensors,
+    ShieldGenerator,
+}
+
+impl Subsystem {
+    /// All the subsystems, in the order they are reported
+    pub const ALL: [Subsystem; 6] = {
+        use Subsystem::*;
+        [
+            Engines,
+            Lasers,
+            Torpedoes,
+            ShortRangeSensors,
+            LongRangeSensors,
+            ShieldGenerator,
+        ]
+    };
+}
+
+impl DisplayBytes for Subsystem {
+    fn display_bytes(&self) -> Vec<u8> {
+        use Subsystem::*;
+        match self {
+            Engines => b"WARP ENGINES".to_vec(),
+            Lasers => b"LASERS".to_vec(),
+            Torpedoes => b"TORPEDO TUBES".to_vec(),
+            ShortRangeSensors => b"SHORT RANGE SENSORS".to_vec(),
+            LongRangeSensors => b"LONG RANGE SENSORS".to_vec(),
+            ShieldGenerator => b"SHIELD GENERATOR".to_vec(),
+        }
+    }
+}
+
+/// State of repair of the player's subsystems, as the time
+/// left to repair each one (zero for those working)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Systems([u8; Subsystem::ALL.len()]);
+
+impl Systems {
+    /// Restores the subsystems from their repair times, in the
+    /// order of `Subsystem::ALL`
+    pub fn new(repairs: [u8; Subsystem::ALL.len()]) -> Self {
+        Self(repairs)
+    }
+
+    /// Returns whether the subsystem is working
+    pub fn working(&self, system: Subsystem) -> bool {
+        self.0[system as usize] == 0
+    }
+
+    /// Returns the time left to repair the subsystem
+    pub fn repair_time(&self, system: Subsystem) -> u8 {
+        self.0[system as usize]
+    }
+
+    /// Returns the time left to repair each subsystem, in the
+    /// order of `Subsystem::ALL`
+    pub fn repair_times(&self) -> [u8; Subsystem::ALL.len()] {
+        self.0
+    }
+
+    /// Returns whether any subsystem is damaged
+    pub fn damaged(&self) -> bool {
+        self.0.iter().any(|&t| t > 0)
+    }
+
+    /// Knocks out the subsystem for at least the given time
+    pub fn disable(&mut self, system: Subsystem, time: u8) {
+        let repair = &mut self.0[system as usize];
+        *repair = (*repair).max(time);
+    }
+
+    /// Makes a period's worth of repairs to every damaged subsystem
+    pub fn repair(&mut self) {
+        for repair in self.0.iter_mut() {
+            *repair = repair.saturating_sub(1);
+        }
+    }
+
+    /// Repairs every subsystem at once
+    pub fn repair_all(&mut self) {
+        self.0 = Default::default();
+    }
+}
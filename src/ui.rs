@@ -1,22 +1,77 @@
-use crate::constants::*;
+use crate::{constants::*, Attribute, Colour, History};
+use std::ops::Range;
 
 /// A terminal-like display offering a command-line
 /// interface
 pub struct Terminal {
     buffer: [u8; WIDTH * HEIGHT],
-    history: [[u8; WIDTH - CWIDTH]; HIST],
-    line: usize,
+    /// The line being entered, which may be a copy of one from the history
+    edit: [u8; WIDTH - CWIDTH],
+    /// The line being entered before looking through the history
+    draft: [u8; WIDTH - CWIDTH],
+    history: History,
+    /// How long ago the line being edited was entered, if it is from the history
+    recalled: Option<usize>,
+    /// A search back through the history in progress
+    search: Option<Search>,
     cursor: usize,
+    /// Whether input is inserted at the cursor, rather than
+    /// overwriting the character there
+    insert: bool,
+    /// Shown after the input, e.g. the arguments of the command
+    hint: Vec<u8>,
+    /// How each tile of the display is drawn
+    attributes: [Attribute; WIDTH * HEIGHT],
+    /// The lines of the latest message, which may be too many for the screen
+    output: Vec<Line>,
+    /// The line of the message at the top of the screen
+    top: usize,
+    /// The display as the frontend last drew it, if it has
+    drawn: Option<Frame>,
+}
+
+/// A copy of the display, to tell what has changed since
+struct Frame {
+    buffer: [u8; WIDTH * HEIGHT],
+    attributes: [Attribute; WIDTH * HEIGHT],
+}
+
+/// A line of a message, as it is laid out on the display
+struct Line {
+    text: Vec<u8>,
+    styles: Vec<Attribute>,
+}
+
+/// A search back through the history, refined as it is typed
+struct Search {
+    text: Vec<u8>,
+    /// How long ago the matching line was entered
+    found: Option<usize>,
 }
 
 impl Terminal {
     /// Creates a new instance with blank display
     pub fn new() -> Self {
+        Self::with_history(HIST)
+    }
+
+    /// Creates a new instance with blank display, remembering
+    /// the given number of lines of history
+    pub fn with_history(length: usize) -> Self {
         Self {
             buffer: [0; WIDTH * HEIGHT],
-            history: [[32u8; WIDTH - CWIDTH]; HIST],
-            line: 0,
+            edit: [32; WIDTH - CWIDTH],
+            draft: [32; WIDTH - CWIDTH],
+            history: History::new(length),
+            recalled: None,
+            search: None,
             cursor: 0,
+            insert: true,
+            hint: Vec::new(),
+            attributes: [Attribute::DEFAULT; WIDTH * HEIGHT],
+            output: Vec::new(),
+            top: 0,
+            drawn: None,
         }
     }
 
@@ -30,24 +85,222 @@ impl Terminal {
         self.buffer[SCREEN..].as_ptr()
     }
 
+    /// Returns the whole display buffer, including the command-line
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns how each tile of the display buffer is drawn
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    /// Returns the positions in the display buffer of the tiles
+    /// that have changed since it was last drawn, in order. Every
+    /// tile has changed if it has not been drawn
+    pub fn dirty_cells(&self) -> Vec<u16> {
+        let Some(drawn) = &self.drawn else {
+            return (0..(WIDTH * HEIGHT) as u16).collect();
+        };
+        (0..WIDTH * HEIGHT)
+            .filter(|&i| {
+                self.buffer[i] != drawn.buffer[i] || self.attributes[i] != drawn.attributes[i]
+            })
+            .map(|i| i as u16)
+            .collect()
+    }
+
+    /// Records that the frontend has drawn the display as it is now
+    pub fn mark_drawn(&mut self) {
+        self.drawn = Some(Frame {
+            buffer: self.buffer,
+            attributes: self.attributes,
+        });
+    }
+
+    /// Forgets what the frontend has drawn, e.g. after it was
+    /// cleared, so that the whole display is drawn again
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    /// Returns the rows of the display buffer, excluding the command-line
+    pub fn rows(&self) -> std::slice::Chunks<'_, u8> {
+        self.buffer[..SCREEN].chunks(WIDTH)
+    }
+
+    /// Returns the lines entered so far
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Changes the number of lines of history remembered
+    pub fn set_history_length(&mut self, length: usize) {
+        self.accept();
+        self.history.set_capacity(length);
+        if self.recalled.is_some_and(|age| age >= self.history.len()) {
+            self.recalled = None;
+        }
+    }
+
     /// Receives input, assuming code-page 437 encoding.
     /// Note: CP437 matches ASCII for printable characters.
     pub fn input(&mut self, c: u16) {
-        if c < 256 {
-            self.history[self.line][self.cursor] = c as u8;
+        if c >= 256 {
+            return;
+        }
+        if let Some(search) = &mut self.search {
+            // Refine the search, keeping to the current match if it still fits
+            search.text.push(c as u8);
+            search.found = self.history.search(&search.text, search.found.unwrap_or(0));
+            self.update_console();
+        } else if !self.insert {
+            self.edit[self.cursor] = c as u8;
             self.right();
+        } else if self.edit.last() == Some(&32) {
+            // Inserting only when there is room, so nothing is lost
+            self.splice(self.cursor..self.cursor, &[c as u8]);
+        }
+    }
+
+    /// Switches between inserting and overwriting input
+    pub fn toggle_insert(&mut self) {
+        self.insert = !self.insert;
+        self.update_console();
+    }
+
+    /// Returns whether input is inserted rather than overwriting
+    pub fn inserting(&self) -> bool {
+        self.insert
+    }
+
+    /// Deletes the character before the cursor
+    pub fn backspace(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.text.pop();
+            search.found = self.history.search(&search.text, 0);
+            self.update_console();
+        } else if self.cursor > 0 {
+            self.splice((self.cursor - 1)..self.cursor, b"");
         }
     }
 
+    /// Deletes the character under the cursor
+    pub fn delete(&mut self) {
+        self.accept();
+        self.splice(self.cursor..(self.cursor + 1), b"");
+    }
+
+    /// Deletes the word before the cursor
+    pub fn delete_word(&mut self) {
+        self.accept();
+        let before = &self.edit[..self.cursor];
+        let end = before.trim_ascii_end().len();
+        let start = before[..end]
+            .iter()
+            .rposition(u8::is_ascii_whitespace)
+            .map_or(0, |i| i + 1);
+        self.splice(start..self.cursor, b"");
+    }
+
+    /// Deletes everything before the cursor
+    pub fn kill_line(&mut self) {
+        self.accept();
+        self.splice(0..self.cursor, b"");
+    }
+
+    /// Moves the cursor to the start of the command-line
+    pub fn home(&mut self) {
+        self.accept();
+        self.cursor = 0;
+        self.update_console();
+    }
+
+    /// Moves the cursor to the end of the command-line input
+    pub fn end(&mut self) {
+        self.accept();
+        self.cursor = self.edit.trim_ascii_end().len().min(WIDTH - CWIDTH - 1);
+        self.update_console();
+    }
+
+    /// Returns the current command-line input
+    pub fn line(&self) -> &[u8] {
+        &self.edit
+    }
+
+    /// Replaces the current command-line input with the given text
+    pub fn set_line(&mut self, text: &[u8]) {
+        self.search = None;
+        self.edit.fill(32);
+        let n = text.len().min(self.edit.len());
+        self.edit[..n].copy_from_slice(&text[..n]);
+        self.cursor = 0;
+        self.update_console();
+    }
+
+    /// Returns the position of the cursor in the command-line input
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces part of the command-line input with the given text,
+    /// leaving the cursor after it
+    pub fn splice(&mut self, range: Range<usize>, text: &[u8]) {
+        self.accept();
+        let line = &mut self.edit;
+        let mut spliced = [&line[..range.start], text, &line[range.end..]].concat();
+        spliced.resize(line.len(), 32);
+        line.copy_from_slice(&spliced);
+        self.cursor = (range.start + text.len()).min(line.len() - 1);
+        self.update_console();
+    }
+
+    /// Shows a hint following the command-line input
+    pub fn set_hint(&mut self, hint: &[u8]) {
+        self.hint = hint.to_vec();
+        self.update_console();
+    }
+
     /// Prints the command-line to the display buffer
     pub fn update_console(&mut self) {
-        self.buffer[SCREEN..(SCREEN + CWIDTH)].clone_from_slice(COMMAND);
-        self.buffer[(SCREEN + CWIDTH)..].clone_from_slice(&self.history[self.line]);
-        self.buffer[SCREEN + CWIDTH + self.cursor] = 219;
+        let console = &mut self.buffer[SCREEN..];
+        if let Some(search) = &self.search {
+            // The search text, followed by the line it matches
+            let found = search.found.and_then(|age| self.history.get(age));
+            let row = [
+                b"SEARCH '",
+                &search.text[..],
+                b"' => ",
+                found.unwrap_or_default(),
+            ]
+            .concat();
+            console.fill(32);
+            let n = row.len().min(WIDTH);
+            console[..n].copy_from_slice(&row[..n]);
+            self.attributes[SCREEN..].fill(Attribute::DEFAULT);
+            return;
+        }
+
+        let line = &self.edit;
+        console[..CWIDTH].clone_from_slice(COMMAND);
+        console[CWIDTH..].clone_from_slice(line);
+
+        // The hint goes after the input, as far as there is room,
+        // dimmed to tell it apart
+        let start = CWIDTH + (line.trim_ascii_end().len().max(self.cursor) + 1).min(line.len());
+        let n = self.hint.len().min(WIDTH - start);
+        console[start..(start + n)].copy_from_slice(&self.hint[..n]);
+        let attributes = &mut self.attributes[SCREEN..];
+        attributes.fill(Attribute::DEFAULT);
+        attributes[start..(start + n)].fill(Attribute::fg(Colour::DarkGrey));
+
+        // A full block when inserting, or a half block when overwriting
+        console[CWIDTH + self.cursor] = if self.insert { 219 } else { 220 };
     }
 
     /// Moves the command-line input cursor one character to the left
     pub fn left(&mut self) {
+        self.accept();
         if self.cursor > 0 {
             self.cursor -= 1;
             self.update_console();
@@ -56,58 +309,271 @@ impl Terminal {
 
     /// Moves the command-line input cursor one character to the right
     pub fn right(&mut self) {
+        self.accept();
         if self.cursor < WIDTH - CWIDTH - 1 {
             self.cursor += 1;
             self.update_console();
         }
     }
 
-    /// Cycles forward through the command-line history
+    /// Moves forward through the command-line history, back
+    /// to the line that was being entered
     pub fn down(&mut self) {
-        self.cursor = 0;
-        self.line = (self.line + 1) % HIST;
-        self.update_console();
+        self.accept();
+        match self.recalled {
+            Some(0) => self.recall(None),
+            Some(age) => self.recall(Some(age - 1)),
+            None => (),
+        }
     }
 
-    /// Cycles backward through the command-line history
+    /// Moves backward through the command-line history
     pub fn up(&mut self) {
-        self.cursor = 0;
-        self.line = (self.line - 1) % HIST;
+        self.accept();
+        let age = self.recalled.map_or(0, |age| age + 1);
+        if age < self.history.len() {
+            self.recall(Some(age));
+        }
+    }
+
+    /// Searches back through the history for a line containing the
+    /// text typed next, or if already searching, for an older match
+    pub fn search(&mut self) {
+        match &mut self.search {
+            Some(search) => {
+                let from = search.found.map_or(0, |age| age + 1);
+                if let Some(age) = self.history.search(&search.text, from) {
+                    search.found = Some(age);
+                }
+            }
+            None => {
+                self.search = Some(Search {
+                    text: Vec::new(),
+                    found: self.history.search(b"", 0),
+                })
+            }
+        }
         self.update_console();
     }
 
+    /// Returns whether a search through the history is in progress
+    pub fn searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// Finishes any search, putting the matching line on the
+    /// command-line to be edited or entered
+    pub fn accept(&mut self) {
+        if let Some(Search { found, .. }) = self.search.take() {
+            match found {
+                Some(age) => self.recall(Some(age)),
+                None => self.update_console(),
+            }
+        }
+    }
+
     /// Consumes full line of input as a single command
     pub fn enter(&mut self) -> [u8; WIDTH - CWIDTH] {
-        let ret = self.history[self.line];
-        self.down();
-        self.history[self.line].copy_from_slice(&[32u8; WIDTH - CWIDTH]);
+        self.accept();
+        let ret = self.edit;
+        self.history.push(&ret);
+        self.edit.fill(32);
+        self.recalled = None;
+        self.cursor = 0;
+        self.update_console();
         ret
     }
 
-    /// Print the message to the display, accounting for
-    /// newlines and truncating any long lines
+    /// Puts a copy of a line from the history on the command-line,
+    /// or the line that was being entered before looking through it
+    fn recall(&mut self, age: Option<usize>) {
+        if self.recalled.is_none() {
+            self.draft = self.edit;
+        }
+        match age.and_then(|age| self.history.get(age)) {
+            Some(entry) => {
+                self.edit.fill(32);
+                let n = entry.len().min(self.edit.len());
+                self.edit[..n].copy_from_slice(&entry[..n]);
+            }
+            None => self.edit = self.draft,
+        }
+        self.recalled = age;
+        self.cursor = self.edit.trim_ascii_end().len().min(WIDTH - CWIDTH - 1);
+        self.update_console();
+    }
+
+    /// Print the message to the display, accounting for newlines and
+    /// wrapping any long lines at spaces. A message too long for the
+    /// screen is shown a page at a time, with a `-- MORE --` indicator
     pub fn message(&mut self, msg: &[u8]) {
-        let mut text = msg.iter();
-        for i in 0..HEIGHT {
-            let mut j = 0;
-            for &c in text.by_ref() {
-                if c == b'\n' {
-                    break;
-                }
+        self.show(msg, &[], 0);
+    }
 
-                // Truncate line beyond display width
-                if j < WIDTH {
-                    self.buffer[i * WIDTH + j] = c;
-                }
+    /// Print the message to the display as `message` does, indenting
+    /// wrapped lines by the given amount more than the line they
+    /// continue, e.g. to hang them under a heading
+    pub fn message_indented(&mut self, msg: &[u8], indent: usize) {
+        self.show(msg, &[], indent);
+    }
 
-                j += 1;
-            }
+    /// Print the message to the display as `message` does, drawing
+    /// each character with the attribute at the same position in
+    /// `styles` (or the default attribute, past the end)
+    pub fn message_styled(&mut self, msg: &[u8], styles: &[Attribute]) {
+        self.show(msg, styles, 0);
+    }
 
-            // Blank out rest of line after newline
-            while j < WIDTH {
-                self.buffer[i * WIDTH + j] = 0;
-                j += 1;
+    /// Lays out a message in lines that fit the display, and shows it
+    fn show(&mut self, msg: &[u8], styles: &[Attribute], indent: usize) {
+        self.output.clear();
+        let mut start = 0;
+        for line in msg.split(|&c| c == b'\n') {
+            for (margin, range) in wrap(line, indent) {
+                let range = (start + range.start)..(start + range.end);
+                let style = |i| styles.get(i).copied().unwrap_or_default();
+                let mut row = Line {
+                    text: vec![b' '; margin],
+                    styles: vec![Attribute::DEFAULT; margin],
+                };
+                row.text.extend_from_slice(&msg[range.clone()]);
+                row.styles.extend(range.map(style));
+                self.output.push(row);
             }
+            start += line.len() + 1;
+        }
+        self.top = 0;
+        self.draw_output();
+    }
+
+    /// Returns all the lines of the latest message, including
+    /// any not on the screen
+    pub fn lines(&self) -> impl Iterator<Item = &[u8]> {
+        self.output.iter().map(|line| line.text.as_slice())
+    }
+
+    /// Returns whether there is more of the message below the screen
+    pub fn more(&self) -> bool {
+        self.top < self.last_page()
+    }
+
+    /// Shows the next page of a long message
+    pub fn page_down(&mut self) {
+        self.top = (self.top + PAGE).min(self.last_page());
+        self.draw_output();
+    }
+
+    /// Shows the previous page of a long message
+    pub fn page_up(&mut self) {
+        self.top = self.top.saturating_sub(PAGE);
+        self.draw_output();
+    }
+
+    /// The first line of the last page of the message
+    fn last_page(&self) -> usize {
+        if self.output.len() > ROWS {
+            self.output.len() - PAGE
+        } else {
+            0
+        }
+    }
+
+    /// Draws the part of the message being looked at, followed by
+    /// the paging indicator if it does not all fit
+    fn draw_output(&mut self) {
+        let status: &[u8] = if self.more() {
+            b"-- MORE -- (Space or PgDn)"
+        } else if self.top > 0 {
+            b"-- END -- (PgUp to go back)"
+        } else {
+            b""
+        };
+        let shown = if status.is_empty() { ROWS } else { PAGE };
+        let inverse = [Attribute::DEFAULT.inverse(); WIDTH];
+
+        let lines = self.output.iter().skip(self.top).take(shown);
+        fill(
+            &mut self.buffer[..SCREEN],
+            &mut self.attributes[..SCREEN],
+            lines
+                .map(|line| (line.text.as_slice(), line.styles.as_slice()))
+                .chain([(status, &inverse[..status.len()])]),
+        );
+    }
+
+    /// Fills the whole display with the message, including the
+    /// command-line, as for the title screen. Long lines are wrapped
+    /// as they are in messages
+    pub fn splash(&mut self, msg: &[u8]) {
+        self.output.clear();
+        self.top = 0;
+        let lines: Vec<Vec<u8>> = msg
+            .split(|&c| c == b'\n')
+            .flat_map(|line| {
+                wrap(line, 0)
+                    .into_iter()
+                    .map(|(margin, range)| [&vec![b' '; margin], &line[range]].concat())
+            })
+            .collect();
+        fill(
+            &mut self.buffer,
+            &mut self.attributes,
+            lines.iter().map(|line| (line.as_slice(), &[][..])),
+        );
+    }
+}
+
+/// Breaks a line of text into rows that fit the display, at spaces
+/// where possible, returning the part of the line in each row and
+/// the margin before it. Rows after the first keep the line's own
+/// leading spaces, plus the given indent
+fn wrap(line: &[u8], indent: usize) -> Vec<(usize, Range<usize>)> {
+    let lead = line.iter().take_while(|&&c| c == b' ').count();
+    let indent = (lead + indent).min(WIDTH / 2);
+    let spaces = |from: usize| from + line[from..].iter().take_while(|&&c| c == b' ').count();
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut margin = 0;
+    loop {
+        let width = WIDTH - margin;
+        if line.len() - start <= width {
+            rows.push((margin, start..line.len()));
+            return rows;
+        }
+
+        // Break at the last space that fits, after any leading spaces
+        let rest = &line[start..];
+        let (end, next) = match rest[..=width].iter().rposition(|&c| c == b' ') {
+            Some(i) if start + i > spaces(start) => (start + i, start + i + 1),
+            _ => (start + width, start + width),
+        };
+        let end = start + line[start..end].trim_ascii_end().len();
+        rows.push((margin, start..end));
+
+        start = spaces(next);
+        if start == line.len() {
+            return rows;
+        }
+        margin = indent;
+    }
+}
+
+/// Draws lines of text into rows of the display, truncating any
+/// long lines and blanking the rows after them
+fn fill<'a>(
+    rows: &mut [u8],
+    attributes: &mut [Attribute],
+    lines: impl Iterator<Item = (&'a [u8], &'a [Attribute])>,
+) {
+    let mut lines = lines.fuse();
+    for (row, attributes) in rows.chunks_mut(WIDTH).zip(attributes.chunks_mut(WIDTH)) {
+        let (line, styles) = lines.next().unwrap_or_default();
+        let n = line.len().min(WIDTH);
+        row[..n].copy_from_slice(&line[..n]);
+        row[n..].fill(0);
+        for (i, attribute) in attributes.iter_mut().enumerate() {
+            *attribute = styles.get(i).copied().unwrap_or_default();
         }
     }
 }
@@ -0,0 +1,77 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use star_trip::{Capacity, Ship, MAX_DIFFICULTY, MIN_DIFFICULTY};
+
+#[test]
+fn supplies_stay_within_capacity() {
+    let mut ship = Ship::player();
+    assert!(ship.is_valid());
+
+    // Hits come off the shields first, and never wrap around
+    assert_eq!(ship.absorb(300), 300);
+    assert_eq!((ship.energy, ship.shields), (210, 0));
+    assert_eq!(ship.absorb(u16::MAX), 210);
+    assert_eq!(ship.energy, 0);
+
+    // Topping up stops at the capacity
+    ship.refit();
+    ship.drain(100);
+    assert_eq!(ship.charge(u16::MAX), 100);
+    assert_eq!(ship.energy, ship.capacity.energy);
+
+    // Only the energy there is room for is diverted to the shields
+    ship.absorb(50);
+    assert_eq!(ship.divert(80), 50);
+    assert_eq!(ship.energy, ship.capacity.energy - 50);
+    assert!(ship.is_valid());
+}
+
+#[test]
+fn ships_can_be_built_bigger() {
+    let mut ship = Ship {
+        capacity: Capacity {
+            energy: 1000,
+            shields: 500,
+            torpedoes: 12,
+        },
+        ..Ship::player()
+    };
+    ship.refit();
+    assert_eq!((ship.energy, ship.shields, ship.torpedoes), (1000, 500, 12));
+    assert!(ship.is_valid());
+
+    ship.capacity.energy = 10;
+    assert!(!ship.is_valid());
+}
+
+#[test]
+fn factions_have_their_own_strengths() {
+    let mut rng = ChaCha8Rng::seed_from_u64(6);
+    for difficulty in [MIN_DIFFICULTY, MAX_DIFFICULTY] {
+        let d = u16::from(difficulty);
+        for _ in 0..100 {
+            let klargons = Ship::klargons(&mut rng, difficulty);
+            assert!(klargons.shields < d / 2 && klargons.torpedoes >= 2);
+
+            let remulins = Ship::remulins(&mut rng, difficulty);
+            assert!(remulins.range < 3);
+
+            // Faringa carry no weapons, but can hoard energy
+            let faringa = Ship::faringa(&mut rng, difficulty);
+            assert_eq!(faringa.torpedoes, 0);
+            assert!(faringa.capacity.energy > faringa.energy);
+            assert!(faringa.energy >= faringa.capacity.energy / 4);
+
+            let berg = Ship::berg(&mut rng, difficulty);
+            assert!(berg.shields >= d / 2);
+
+            // Klargons and Faringa outpace the others
+            assert!(faringa.speed > klargons.speed && klargons.speed > berg.speed);
+            assert_eq!(remulins.speed, berg.speed);
+
+            for ship in [klargons, remulins, faringa, berg] {
+                assert!(ship.is_valid() && ship.tuned.is_none());
+            }
+        }
+    }
+}
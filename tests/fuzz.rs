@@ -0,0 +1,255 @@
+//! Property tests feeding random input into the game, checking that
+//! no command line, keystroke or damaged save can crash it. Each case
+//! is generated from a fixed seed, so any failure is reproducible.
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use star_trip::{Difficulty, Game, GameState, Options, Replay, Terminal};
+
+const CASES: u64 = 64;
+const LINES: usize = 200;
+
+const COMMANDS: &[&str] = &[
+    "help",
+    "h",
+    "move",
+    "m",
+    "MOVE",
+    "laser",
+    "l",
+    "torpedo",
+    "t",
+    "shields",
+    "sh",
+    "scan",
+    "sc",
+    "survey",
+    "su",
+    "investigate",
+    "i",
+    "dock",
+    "damage",
+    "d",
+    "log",
+    "define",
+    "macros",
+    "alias",
+    "unalias",
+    ";",
+    "=",
+    "alpha",
+    "w",
+];
+
+const NUMBERS: &[&str] = &[
+    "0",
+    "1",
+    "9",
+    "10",
+    "19",
+    "20",
+    "255",
+    "256",
+    "65536",
+    "4294967296",
+    "18446744073709551615",
+    "18446744073709551616",
+    "-1",
+    "1.5",
+];
+
+/// Names for macros and aliases, few enough that they often clash
+const NAMES: &[&str] = &["x", "y", "alpha"];
+
+const WHITESPACE: &[u8] = b"  \t\n\r\x0b\x0c";
+
+/// Generates random options, including out of range sizes
+fn options(rng: &mut ChaCha8Rng) -> Options {
+    Options {
+        difficulty: Difficulty::from_value(rng.gen()),
+        systems: rng.gen_range(0..30),
+        sectors: rng.gen_range(0..30),
+    }
+}
+
+/// Generates a single word of a command line
+fn word(rng: &mut ChaCha8Rng) -> Vec<u8> {
+    match rng.gen_range(0..10) {
+        0..=3 => rng.gen_range(0..25usize).to_string().into_bytes(),
+        4..=5 => NUMBERS.choose(rng).unwrap().as_bytes().to_vec(),
+        6..=7 => COMMANDS.choose(rng).unwrap().as_bytes().to_vec(),
+        _ => (0..rng.gen_range(1..8)).map(|_| rng.gen()).collect(),
+    }
+}
+
+/// Generates `ALIAS` and `UNALIAS` commands, each followed by `;`
+fn renames(rng: &mut ChaCha8Rng) -> String {
+    let mut out = String::new();
+    for _ in 0..rng.gen_range(0..3) {
+        let name = NAMES.choose(rng).unwrap();
+        if rng.gen_bool(0.7) {
+            out += &format!("alias {name} scan; ");
+        } else {
+            out += &format!("unalias {name}; ");
+        }
+    }
+    out
+}
+
+/// Generates a line naming or using macros and aliases, including
+/// several in one line and aliases given within macros
+fn naming(rng: &mut ChaCha8Rng) -> Vec<u8> {
+    let mut line = renames(rng);
+    let name = NAMES.choose(rng).unwrap();
+    match rng.gen_range(0..3) {
+        0 => line += &format!("define {name} = {}scan", renames(rng)),
+        1 => line += &format!("define {name} ="),
+        _ => line += name,
+    }
+    line.into_bytes()
+}
+
+/// Generates a command line, usually starting with a real command
+fn line(rng: &mut ChaCha8Rng) -> Vec<u8> {
+    if rng.gen_bool(0.2) {
+        return naming(rng);
+    }
+    let mut line = if rng.gen_bool(0.9) {
+        COMMANDS.choose(rng).unwrap().as_bytes().to_vec()
+    } else {
+        word(rng)
+    };
+    for _ in 0..rng.gen_range(0..6) {
+        line.push(*WHITESPACE.choose(rng).unwrap());
+        line.extend(word(rng));
+    }
+    line
+}
+
+/// Recomputes the trailing checksum, so that damaged data
+/// gets past the integrity check and into the parser
+fn reseal(data: &mut Vec<u8>) {
+    data.truncate(data.len().saturating_sub(4));
+    let sum = data.iter().fold(0x811c9dc5u32, |hash, &c| {
+        (hash ^ c as u32).wrapping_mul(0x01000193)
+    });
+    data.extend_from_slice(&sum.to_le_bytes());
+}
+
+/// Damages data by flipping, inserting, removing and truncating bytes
+fn damage(rng: &mut ChaCha8Rng, data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    for _ in 0..rng.gen_range(1..4) {
+        let i = rng.gen_range(0..data.len());
+        match rng.gen_range(0..4) {
+            0 => data[i] ^= 1 << rng.gen_range(0..8),
+            1 => data[i] = rng.gen(),
+            2 => data.insert(i, rng.gen()),
+            _ => {
+                data.remove(i);
+            }
+        }
+    }
+    if rng.gen_bool(0.1) {
+        data.truncate(rng.gen_range(0..data.len()));
+    }
+    if rng.gen_bool(0.8) {
+        reseal(&mut data);
+    }
+    data
+}
+
+#[test]
+fn random_commands_never_panic() {
+    for case in 0..CASES {
+        let mut rng = ChaCha8Rng::seed_from_u64(case);
+        let mut state = GameState::new_with_options(rng.gen(), options(&mut rng));
+        let mut term = Terminal::new();
+
+        for _ in 0..LINES {
+            let status = state.process_command(&line(&mut rng), &mut term);
+            assert!(status <= 2, "case {case}: bad status {status}");
+        }
+
+        let restored = GameState::load(&state.save()).ok().unwrap();
+        assert_eq!(restored.save(), state.save(), "case {case}");
+    }
+}
+
+#[test]
+fn random_keys_never_panic() {
+    for case in 0..CASES {
+        let mut rng = ChaCha8Rng::seed_from_u64(case);
+        let mut game = Game::with_options(rng.gen(), options(&mut rng));
+        game.intro();
+
+        for _ in 0..(10 * LINES) {
+            match rng.gen_range(0..20) {
+                0 => game.left(),
+                1 => game.right(),
+                2 => game.up(),
+                3 => game.down(),
+                4 => match game.enter() {
+                    1 => game.win(),
+                    2 => game.lose(),
+                    _ => (),
+                },
+                5 => game.input(rng.gen()),
+                6..=9 => {
+                    for &c in &line(&mut rng) {
+                        game.input(c as u16);
+                    }
+                }
+                10 => {
+                    game.complete();
+                }
+                11 => match rng.gen_range(0..11) {
+                    0 => game.backspace(),
+                    1 => game.delete(),
+                    2 => game.home(),
+                    3 => game.end(),
+                    4 => game.delete_word(),
+                    5 => game.kill_line(),
+                    6 => game.toggle_insert(),
+                    7 => game.set_history_length(rng.gen_range(0..20)),
+                    8 => game.page_up(),
+                    9 => game.page_down(),
+                    _ => game.search(),
+                },
+                _ => game.input(*b"0123456789 ".choose(&mut rng).unwrap() as u16),
+            }
+        }
+    }
+}
+
+#[test]
+fn damaged_data_never_panics() {
+    for case in 0..CASES {
+        // Smaller galaxies keep the saves quick to damage and load
+        let mut rng = ChaCha8Rng::seed_from_u64(case);
+        let options = Options {
+            systems: rng.gen_range(0..10),
+            sectors: rng.gen_range(0..10),
+            ..options(&mut rng)
+        };
+        let mut state = GameState::new_with_options(rng.gen(), options);
+        state.start_recording();
+        let mut term = Terminal::new();
+        for _ in 0..20 {
+            state.process_command(&line(&mut rng), &mut term);
+        }
+
+        let save = state.save();
+        let replay = state.replay().map(Replay::export).unwrap();
+        for _ in 0..(LINES / 2) {
+            // Any damaged save that does load must still be playable
+            if let Ok(mut loaded) = GameState::load(&damage(&mut rng, &save)) {
+                for _ in 0..5 {
+                    loaded.process_command(&line(&mut rng), &mut term);
+                }
+            }
+
+            let _ = Replay::import(&damage(&mut rng, &replay));
+        }
+    }
+}
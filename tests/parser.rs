@@ -0,0 +1,158 @@
+use star_trip::{
+    completions, hint, parse_line, Aliases, Command, CommandSpec, Macros, ParseErrorKind,
+};
+
+#[test]
+fn commands_have_typed_arguments() {
+    assert_eq!(Command::parse(b"   "), Ok(None));
+    assert_eq!(Command::parse(b"SC"), Ok(Some(Command::Scan)));
+    assert_eq!(
+        Command::parse(b"move 3 4 5"),
+        Ok(Some(Command::Move {
+            speed: 3,
+            x: 4,
+            y: 5,
+            system: None
+        }))
+    );
+    assert_eq!(
+        Command::parse(b"M\t1 2 3  4 5"),
+        Ok(Some(Command::Move {
+            speed: 1,
+            x: 2,
+            y: 3,
+            system: Some((4, 5))
+        }))
+    );
+    assert_eq!(
+        Command::parse(b"log"),
+        Ok(Some(Command::Log { page: None }))
+    );
+    assert_eq!(
+        CommandSpec::find(b"Torpedo").map(|s| s.usage()),
+        Some(b"TORPEDO t x y".to_vec())
+    );
+}
+
+#[test]
+fn errors_pinpoint_the_bad_word() {
+    let err = Command::parse(b"MOVE 3 x 4").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::NotANumber(b"x".to_vec()));
+    assert_eq!(err.span, 7..8);
+
+    let err = Command::parse(b"warp 9").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Unknown(b"warp".to_vec()));
+    assert_eq!(err.span, 0..4);
+
+    let err = Command::parse(b"move 1 2 3 4").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Missing(b"Y"));
+    assert_eq!(err.span, 13..13);
+
+    let err = Command::parse(b"scan 1").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Unexpected);
+    assert_eq!(err.span, 5..6);
+
+    let err = Command::parse(b"laser 0 1 1").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Zero);
+
+    let explained = err.explain(b"laser 0 1 1");
+    assert!(explained.starts_with(b"    laser 0 1 1\n          ^\n"));
+}
+
+#[test]
+fn chains_and_macros_expand() {
+    let mut macros = Macros::default();
+    assert_eq!(
+        parse_line(
+            b"DEFINE alpha = SH 50; L 40 3 4",
+            &macros,
+            &Aliases::default()
+        ),
+        Ok(vec![Command::Define {
+            name: b"ALPHA".to_vec(),
+            body: b"SH 50; L 40 3 4".to_vec()
+        }])
+    );
+    macros.define(b"ALPHA", b"SH 50; L 40 3 4");
+
+    assert_eq!(
+        parse_line(b"alpha; scan;", &macros, &Aliases::default()),
+        Ok(vec![
+            Command::Shields { energy: 50 },
+            Command::Laser {
+                energy: 40,
+                x: 3,
+                y: 4
+            },
+            Command::Scan
+        ])
+    );
+    assert_eq!(parse_line(b" ; ", &macros, &Aliases::default()), Ok(vec![]));
+
+    let err = parse_line(b"scan; move 1 x 2", &macros, &Aliases::default()).unwrap_err();
+    assert_eq!(err.span, 13..14);
+
+    let err = parse_line(b"define sc = scan", &macros, &Aliases::default()).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Reserved(b"SC".to_vec()));
+
+    let err = parse_line(b"define beta = alpha", &macros, &Aliases::default()).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Unknown(b"alpha".to_vec()));
+}
+
+#[test]
+fn aliases_take_the_place_of_commands() {
+    let mut aliases = Aliases::default();
+    let laser = CommandSpec::find(b"laser").unwrap();
+    assert_eq!(
+        Command::parse(b"alias zap l"),
+        Ok(Some(Command::Alias {
+            name: b"ZAP".to_vec(),
+            command: laser
+        }))
+    );
+    aliases.define(b"ZAP", laser);
+
+    let macros = Macros::default();
+    assert_eq!(
+        parse_line(b"zap 5 1 2", &macros, &aliases),
+        Ok(vec![Command::Laser {
+            energy: 5,
+            x: 1,
+            y: 2
+        }])
+    );
+
+    // Errors give the usage of the command the alias stands for
+    let err = parse_line(b"zap 5 1", &macros, &aliases).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Missing(b"y"));
+    assert_eq!(err.spec, Some(laser));
+
+    let err = Command::parse(b"alias t move").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Reserved(b"T".to_vec()));
+
+    let err = Command::parse(b"alias w warp").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Unknown(b"warp".to_vec()));
+    assert_eq!(err.span, 8..12);
+
+    let err = parse_line(b"scan; define zap = scan", &macros, &aliases).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Taken(b"ZAP".to_vec()));
+    assert_eq!(err.span, 13..16);
+}
+
+#[test]
+fn completions_and_hints_follow_the_commands() {
+    let mut macros = Macros::default();
+    macros.define(b"MOOR", b"D");
+    let aliases = Aliases::default();
+    assert_eq!(
+        completions(b"mo", &macros, &aliases),
+        vec![b"MOVE".to_vec(), b"MOOR".to_vec()]
+    );
+    assert_eq!(completions(b"q", &macros, &aliases), Vec::<Vec<u8>>::new());
+
+    assert_eq!(hint(b"MOVE", &aliases), b"s x y [X Y]");
+    assert_eq!(hint(b"scan; m 3 4 ", &aliases), b"y [X Y]");
+    assert_eq!(hint(b"move 1 2 3 4", &aliases), b"[Y]");
+    assert_eq!(hint(b"define a = sc; l", &aliases), b"e x y");
+    assert_eq!(hint(b"warp", &aliases), b"");
+}
@@ -1,18 +1,278 @@
-#![cfg(target_arch = "wasm32")]
+use star_trip::{char_to_cp437, cp437_to_char, Game, Terminal, CWIDTH, HEIGHT, SCREEN, WIDTH};
 
-use star_trip::Terminal;
-use wasm_bindgen_test::*;
+/// Types a line of text onto the command-line
+fn type_line(term: &mut Terminal, text: &[u8]) {
+    for &c in text {
+        term.input(c as u16);
+    }
+}
+
+#[test]
+fn input_is_echoed_on_console() {
+    let mut term = Terminal::new();
+    type_line(&mut term, b"scan");
+
+    let console = &term.buffer()[SCREEN..];
+    assert_eq!(&console[CWIDTH..(CWIDTH + 4)], b"scan");
+    // Cursor follows the input
+    assert_eq!(console[CWIDTH + 4], 219);
+}
+
+#[test]
+fn typed_characters_map_to_cp437() {
+    for c in (0x20..=0x7E).chain(0x80..=0xFF) {
+        assert_eq!(char_to_cp437(cp437_to_char(c)), Some(c));
+    }
+    assert_eq!(char_to_cp437('é'), Some(0x82));
+    assert_eq!(char_to_cp437('\u{ff}'), Some(0x98));
+    assert_eq!(char_to_cp437('\u{80}'), None);
+    assert_eq!(char_to_cp437('€'), None);
+}
+
+#[test]
+fn enter_consumes_line() {
+    let mut term = Terminal::new();
+    type_line(&mut term, b"help");
+
+    let line = term.enter();
+    assert_eq!(&line[..4], b"help");
+    assert!(line[4..].iter().all(|&c| c == b' '));
+
+    // The next line starts empty, and the previous one is in the history
+    assert!(term.enter().iter().all(|&c| c == b' '));
+    term.up();
+    term.up();
+    assert_eq!(&term.enter()[..4], b"help");
+}
+
+#[test]
+fn message_wraps_lines() {
+    let mut term = Terminal::new();
+    let long = [b'x'; WIDTH + 10];
+    term.message(&[&b"one\ntwo\n"[..], &long].concat());
+
+    let rows: Vec<&[u8]> = term.rows().collect();
+    assert_eq!(rows.len(), HEIGHT - 1);
+    assert_eq!(&rows[0][..4], b"one\0");
+    assert_eq!(&rows[1][..4], b"two\0");
+    // A word too long for a row is broken where it must be
+    assert_eq!(rows[2], &long[..WIDTH]);
+    assert_eq!(&rows[3][..11], b"xxxxxxxxxx\0");
+    assert!(rows[4].iter().all(|&c| c == 0));
+
+    // Otherwise lines break between words, indented to
+    // line up with the start of the line
+    let words = "  word".repeat(WIDTH / 5);
+    term.message_indented(words.as_bytes(), 2);
+    let rows: Vec<&[u8]> = term.rows().collect();
+    assert!(rows[0].starts_with(b"  word  word"));
+    assert!(rows[0].ends_with(b"word\0\0"));
+    assert!(rows[1].starts_with(b"    word  word"));
+}
+
+#[test]
+fn splash_wraps_lines() {
+    let mut term = Terminal::new();
+    let words = "word ".repeat(WIDTH / 4);
+    term.splash(format!("title\n{words}").as_bytes());
+
+    let rows: Vec<&[u8]> = term.buffer().chunks(WIDTH).collect();
+    assert_eq!(&rows[0][..6], b"title\0");
+    assert!(rows[1].ends_with(b"word\0"));
+    assert!(rows[2].starts_with(b"word word"));
+}
+
+#[test]
+fn tab_completes_and_hints() {
+    let mut game = Game::new_with_seed(6);
+    let console = |game: &Game| game.terminal().buffer()[(SCREEN + CWIDTH)..].to_vec();
+
+    for &c in b"sc; su" {
+        game.input(c as u16);
+    }
+    assert!(!game.complete());
+    assert!(console(&game).starts_with(b"sc; SURVEY \xdb"));
+
+    // Ambiguous names are listed rather than completed
+    for &c in b"; m" {
+        game.input(c as u16);
+    }
+    assert!(game.complete());
+    assert!(game
+        .terminal()
+        .rows()
+        .any(|row| row.starts_with(b"    MOVE MACROS")));
+
+    game.input(b'o' as u16);
+    assert!(!game.complete());
+    assert!(console(&game).starts_with(b"sc; SURVEY ; MOVE \xdbs x y [X Y]"));
+}
+
+#[test]
+fn editing_shifts_the_line() {
+    let mut term = Terminal::new();
+    type_line(&mut term, b"move 3 4");
+    term.home();
+    type_line(&mut term, b"m");
+    for _ in 0..4 {
+        term.delete();
+    }
+    assert!(term.line().starts_with(b"m 3 4 "));
+
+    term.end();
+    type_line(&mut term, b" 9");
+    term.backspace();
+    type_line(&mut term, b"5");
+    assert!(term.line().starts_with(b"m 3 4 5 "));
+
+    // Overwriting replaces the character under the cursor
+    term.toggle_insert();
+    term.left();
+    type_line(&mut term, b"6");
+    assert!(term.line().starts_with(b"m 3 4 6 "));
+    assert_eq!(term.buffer()[SCREEN + CWIDTH + term.cursor()], 220);
+
+    term.delete_word();
+    assert!(term.line().starts_with(b"m 3 4  "));
+    term.delete_word();
+    assert!(term.line().starts_with(b"m 3  "));
+    term.kill_line();
+    assert!(term.line().iter().all(|&c| c == b' '));
+}
+
+#[test]
+fn history_is_kept_apart_from_editing() {
+    let mut term = Terminal::with_history(3);
+    for line in [
+        &b"scan"[..],
+        b"move 1 2 2",
+        b"scan",
+        b"",
+        b"laser 5 1 1",
+        b"dock",
+    ] {
+        type_line(&mut term, line);
+        term.enter();
+    }
+    // Blank and repeated lines are not kept, and only the latest few
+    let history: Vec<&[u8]> = term.history().iter().collect();
+    assert_eq!(history, [&b"dock"[..], b"laser 5 1 1", b"scan"]);
+
+    // Editing a recalled line leaves the history alone, and
+    // the line being entered is there to go back to
+    type_line(&mut term, b"su");
+    term.up();
+    term.up();
+    term.backspace();
+    assert!(term.line().starts_with(b"laser 5 1  "));
+    assert_eq!(term.history().get(1), Some(&b"laser 5 1 1"[..]));
+    term.down();
+    term.down();
+    assert!(term.line().starts_with(b"su "));
+
+    // Searching shows the match in place of the command-line
+    term.search();
+    type_line(&mut term, b"a");
+    assert!(term.buffer()[SCREEN..].starts_with(b"SEARCH 'a' => laser 5 1 1"));
+    term.search();
+    assert!(term.buffer()[SCREEN..].starts_with(b"SEARCH 'a' => scan"));
+    term.end();
+    assert!(!term.searching());
+    assert!(term.line().starts_with(b"scan "));
+    assert_eq!(&term.enter()[..5], b"scan ");
+}
+
+#[test]
+fn long_messages_are_paged() {
+    let mut term = Terminal::new();
+    let lines: Vec<String> = (0..50).map(|i| format!("line {i}")).collect();
+    term.message(lines.join("\n").as_bytes());
+
+    let rows = |term: &Terminal| -> Vec<Vec<u8>> {
+        term.rows()
+            .map(|row| row.iter().copied().take_while(|&c| c != 0).collect())
+            .collect()
+    };
+    assert!(term.more());
+    assert_eq!(rows(&term)[0], b"line 0");
+    assert!(rows(&term)[HEIGHT - 2].starts_with(b"-- MORE --"));
+
+    term.page_down();
+    assert_eq!(rows(&term)[0], b"line 23");
+    term.page_down();
+    term.page_down();
+    assert!(!term.more());
+    assert_eq!(rows(&term)[HEIGHT - 3], b"line 49");
+    assert!(rows(&term)[HEIGHT - 2].starts_with(b"-- END --"));
+
+    term.page_up();
+    assert_eq!(rows(&term)[0], b"line 4");
+
+    // Short messages fill the screen without an indicator
+    term.message(b"short");
+    assert!(!term.more());
+    assert!(rows(&term)[1..].iter().all(Vec::is_empty));
+}
+
+#[test]
+fn only_changes_are_drawn() {
+    let mut term = Terminal::new();
+    assert_eq!(term.dirty_cells().len(), WIDTH * HEIGHT);
+    term.update_console();
+    term.mark_drawn();
+    assert!(term.dirty_cells().is_empty());
+
+    // Typing only changes the letter and the cursor after it
+    type_line(&mut term, b"s");
+    let console = SCREEN + CWIDTH;
+    assert_eq!(term.dirty_cells(), [console as u16, console as u16 + 1]);
+    term.mark_drawn();
+
+    term.message(b"hi");
+    assert_eq!(term.dirty_cells(), [0, 1]);
+
+    term.invalidate();
+    assert_eq!(term.dirty_cells().len(), WIDTH * HEIGHT);
+
+    // The game hands over each change once
+    let mut game = Game::new_with_seed(6);
+    assert_eq!(game.dirty_cells().len(), WIDTH * HEIGHT);
+    assert!(game.dirty_cells().is_empty());
+}
+
+#[test]
+fn loading_ends_playback() {
+    let mut recorded = Game::new_with_seed(6);
+    for line in [&b"scan"[..], b"survey"] {
+        for &c in line {
+            recorded.input(c as u16);
+        }
+        recorded.enter();
+    }
+    let replay = recorded.export_replay();
+    let save = recorded.save();
 
-wasm_bindgen_test_configure!(run_in_browser);
+    let mut game = Game::new_with_seed(7);
+    assert!(game.import_replay(&replay));
+    assert!(game.replaying());
 
-// TODO: write some tests!
+    // A failed load changes nothing
+    assert!(!game.load(b"nonsense"));
+    assert!(game.replaying());
+    assert!(game
+        .terminal()
+        .lines()
+        .any(|line| line.starts_with(b"Your current game continues.")));
 
-#[cfg(test)]
-pub fn two() -> usize {
-    2
+    assert!(game.load(&save));
+    assert!(!game.replaying());
+    assert!(game.terminal().line().iter().all(|&c| c == b' '));
 }
 
-#[wasm_bindgen_test]
-fn pass() {
-    assert_eq!(1 + 1, two());
+#[test]
+fn unknown_difficulty_is_rejected() {
+    assert!(Game::new_with_options(1, "Captain", 10, 10).is_ok());
+    assert!(Game::new_with_options(1, "7", 10, 10).is_ok());
+    let err = Game::new_with_options(1, "admrial", 10, 10).err();
+    assert!(err.is_some_and(|e| e.contains("admrial")));
 }
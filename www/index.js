@@ -2,6 +2,9 @@
 import init, { Game } from './pkg/star_trip.js';
 const wasm = await init();
 
+// Local storage key for the game in progress
+const SAVE_KEY = "star_trip_save";
+
 // Global variables
 let game,       // binding to Rust game engine
     canvas,     // HTML canvas used for rendering
@@ -15,12 +18,56 @@ let game,       // binding to Rust game engine
     delay_time, // dramatic pause for rendering in ms
     started,    // boolean for whether game has started
     delay,      // toggle for stylised drawing
-    drawing;    // toggle during rendering
+    drawing,    // toggle during rendering
+    blink;      // whether blinking tiles are currently shown
+
+// The 16-colour CGA palette, indexed by the colours of an attribute
+const PALETTE = [
+  "#000000", "#0000aa", "#00aa00", "#00aaaa",
+  "#aa0000", "#aa00aa", "#aa5500", "#aaaaaa",
+  "#555555", "#5555ff", "#55ff55", "#55ffff",
+  "#ff5555", "#ff55ff", "#ffff55", "#ffffff",
+];
+
+// Bits of an attribute (see Attribute)
+const BOLD = 1 << 8;
+const BLINK = 1 << 9;
+const INVERSE = 1 << 10;
+
+// Time between blinks in ms
+const BLINK_TIME = 500;
 
 // Initialises game state and sets up browser event handlers
 function gameSetup() {
-  // Game configuration
-  game = Game.new();
+  // Game configuration, optionally from a shared seed and options
+  // (e.g. index.html?seed=1234&difficulty=captain&systems=5&sectors=8),
+  // and how many commands to remember (e.g. &history=50)
+  const params = new URLSearchParams(window.location.search);
+  const seed = params.get("seed");
+  const difficulty = params.get("difficulty");
+  const systems = params.get("systems");
+  const sectors = params.get("sectors");
+  if (difficulty !== null || systems !== null || sectors !== null) {
+    const random = crypto.getRandomValues(new BigUint64Array(1))[0];
+    const start = seed === null ? random : BigInt(seed);
+    try {
+      game = Game.new_with_options(start, difficulty ?? "lieutenant",
+                                   Number(systems ?? 10),
+                                   Number(sectors ?? 10));
+    } catch (err) {
+      // Report the mistaken option, then play at the default difficulty
+      window.alert(err);
+      game = Game.new_with_options(start, "lieutenant",
+                                   Number(systems ?? 10),
+                                   Number(sectors ?? 10));
+    }
+  } else {
+    game = seed === null ? Game.new() : Game.new_with_seed(BigInt(seed));
+  }
+  const history = params.get("history");
+  if (history !== null) {
+    game.set_history_length(Number(history));
+  }
   width = game.width();
   height = game.height();
   tile_size = game.tile_size();
@@ -53,14 +100,20 @@ function gameSetup() {
   started = false;
   delay = true;
   drawing = false;
+  blink = true;
 
   // Start things going once the tileset has loaded
   tiles.addEventListener("load", async function() {
     // Initial screen
     await drawScreen(false);
 
-    // Install handler for clicks
+    // Install handlers for clicks and dropped replay files
     canvas.addEventListener("click", handleClicks);
+    canvas.addEventListener("dragover", (e) => e.preventDefault());
+    canvas.addEventListener("drop", handleDrop);
+
+    // Flash any blinking tiles
+    setInterval(blinkTiles, BLINK_TIME);
   });
 }
 
@@ -83,6 +136,69 @@ function drawTile(tile, row, col) {
                 col * tile_size, row * tile_size, tile_size, tile_size);
 }
 
+// Colours a tile drawn white on black according to its attribute
+function styleTile(attr, row, col) {
+  let fg = attr & 0xF;
+  const bg = (attr >> 4) & 0xF;
+  if (attr & BOLD) {
+    fg |= 8;
+  }
+  if (fg === 15 && bg === 0 && !(attr & INVERSE)) {
+    return;
+  }
+
+  ctx.save();
+  const x = col * tile_size;
+  const y = row * tile_size;
+  if (attr & INVERSE) {
+    // Draw the glyph black on white, so that it takes the
+    // background colour and its surroundings the foreground
+    ctx.globalCompositeOperation = "difference";
+    ctx.fillStyle = "white";
+    ctx.fillRect(x, y, tile_size, tile_size);
+  }
+  ctx.globalCompositeOperation = "multiply";
+  ctx.fillStyle = PALETTE[fg];
+  ctx.fillRect(x, y, tile_size, tile_size);
+  ctx.globalCompositeOperation = "lighten";
+  ctx.fillStyle = PALETTE[bg];
+  ctx.fillRect(x, y, tile_size, tile_size);
+  ctx.restore();
+}
+
+// Draws a tile of the display in its colours, leaving
+// blinking tiles blank while they are hidden
+function drawStyledTile(tile, attr, row, col) {
+  drawTile((attr & BLINK) && !blink ? 0 : tile, row, col);
+  styleTile(attr, row, col);
+}
+
+// Returns views of the display buffer and how each tile of it is
+// drawn, which must be taken again after any call that allocates
+function display() {
+  const screen = new Uint8Array(wasm.memory.buffer, game.screen(),
+                                width * height);
+  const attributes = new Uint16Array(wasm.memory.buffer,
+                                     game.attributes(), width * height);
+  return [screen, attributes];
+}
+
+// Shows or hides the blinking tiles in turn
+function blinkTiles() {
+  if (drawing) {
+    return;
+  }
+  blink = !blink;
+
+  const [screen, attributes] = display();
+  for (let i = 0; i < width * height; i++) {
+    if (attributes[i] & BLINK) {
+      drawStyledTile(screen[i], attributes[i],
+                     Math.floor(i / width), i % width);
+    }
+  }
+}
+
 // Brighten the tile
 function brighten(row, col) {
   ctx.globalCompositeOperation = "lighten";
@@ -91,42 +207,32 @@ function brighten(row, col) {
   ctx.fillRect(col * tile_size, row * tile_size, tile_size, tile_size);
 }
 
-// Clears the screen
-function clearScreen() {
-  for (let i = 0; i < height; i++) {
-    for (let j = 0; j < width; j++) {
-      drawTile(0, i, j);
-    }
-  }
-}
-
-// Renders screen with a specified delay in between each
-// tile to simulate a slow display refresh rate
+// Renders the tiles that have changed since last drawn, with a
+// specified delay in between each tile to simulate a slow display
+// refresh rate
 async function drawScreen(sleep) {
   drawing = true;
-  clearScreen();
 
-  const ptr = game.screen();
-  const screen = new Uint8Array(wasm.memory.buffer, ptr,
-                                width * height);
+  const cells = game.dirty_cells();
+  const [screen, attributes] = display();
 
-  for (let i = 0; i < height; i++) {
-    for (let j = 0; j < width; j++) {
-      const val = screen[i * width + j];
-      if (sleep && delay && !(val === 32 || val === 0)) {
-        ctx.save();
+  for (const i of cells) {
+    const row = Math.floor(i / width);
+    const col = i % width;
+    const val = screen[i];
+    if (sleep && delay && !(val === 32 || val === 0)) {
+      ctx.save();
 
-        // Draw a brightened tile
-        drawTile(val, i, j);
-        brighten(i, j);
+      // Draw a brightened tile
+      drawTile(val, row, col);
+      brighten(row, col);
 
-        // Pause for dramatic effect
-        await new Promise((x) => setTimeout(x, delay_time));
+      // Pause for dramatic effect
+      await new Promise((x) => setTimeout(x, delay_time));
 
-        ctx.restore();
-      }
-      drawTile(val, i, j);
+      ctx.restore();
     }
+    drawStyledTile(val, attributes[i], row, col);
   }
 
   // Reset after possible click modification
@@ -136,13 +242,14 @@ async function drawScreen(sleep) {
   drawing = false;
 }
 
-// Renders command line
+// Renders the changes to the command line at once
 function drawConsole() {
-  const ptr = game.console();
-  const console = new Uint8Array(wasm.memory.buffer, ptr, width);
+  const cells = game.dirty_cells();
+  const [screen, attributes] = display();
 
-  for (let j = 0; j < width; j++) {
-    drawTile(console[j], height - 1, j);
+  for (const i of cells) {
+    drawStyledTile(screen[i], attributes[i],
+                   Math.floor(i / width), i % width);
   }
 }
 
@@ -151,6 +258,25 @@ async function handleKeys(e) {
   if(drawing) {
     // Press a key to skip drawing line-printing animation
     delay = false;
+  } else if(e.ctrlKey && (e.key === "w" || e.key === "u" || e.key === "r")) {
+    // Some browsers reserve Ctrl-W, in which case it cannot be used
+    e.preventDefault();
+    if(e.key === "w") {
+      game.delete_word();
+    } else if(e.key === "u") {
+      game.kill_line();
+    } else {
+      game.search();
+    }
+    drawConsole();
+  } else if(e.key === "PageDown" || (e.key === " " && game.paging())) {
+    e.preventDefault();
+    game.page_down();
+    await drawScreen(false);
+  } else if(e.key === "PageUp") {
+    e.preventDefault();
+    game.page_up();
+    await drawScreen(false);
   } else if([...e.key].length === 1) {
     game.input(e.key.charCodeAt(0)); // UTF-16 code unit
     drawConsole();
@@ -167,13 +293,31 @@ async function handleKeys(e) {
     game.down();
     drawConsole();
   } else if(e.key === "Backspace") {
-    game.left();
-    game.input(32);
-    game.left();
+    game.backspace();
     drawConsole();
   } else if(e.key === "Delete") {
-    game.input(32);
+    game.delete();
+    drawConsole();
+  } else if(e.key === "Home") {
+    game.home();
+    drawConsole();
+  } else if(e.key === "End") {
+    game.end();
+    drawConsole();
+  } else if(e.key === "Insert") {
+    game.toggle_insert();
     drawConsole();
+  } else if(e.key === "Tab") {
+    // Keep focus on the game
+    e.preventDefault();
+    if(game.complete()) {
+      await drawScreen(true);
+    } else {
+      drawConsole();
+    }
+  } else if(e.key === "Enter" && !game.replaying() && !game.validate()) {
+    // Explain the mistake, leaving the command to be corrected
+    await drawScreen(true);
   } else if(e.key === "Enter") {
     status = game.enter();
     if(status > 0) {
@@ -184,20 +328,88 @@ async function handleKeys(e) {
       }
       // Game over
       window.removeEventListener("keydown", handleKeys);
+      localStorage.removeItem(SAVE_KEY);
+      offerReplay();
+    } else if (!game.replaying()) {
+      storeGame();
     }
     await drawScreen(true);
   }
   input.value = "";
 }
 
+// Stashes the game in progress in local storage
+function storeGame() {
+  const bytes = game.save();
+  let data = "";
+  for (let i = 0; i < bytes.length; i++) {
+    data += String.fromCharCode(bytes[i]);
+  }
+  localStorage.setItem(SAVE_KEY, btoa(data));
+}
+
+// Restores a game from local storage, returning whether
+// there was a saved game to restore
+function restoreGame() {
+  const data = localStorage.getItem(SAVE_KEY);
+  if (data === null) {
+    return false;
+  }
+
+  let bytes;
+  try {
+    bytes = Uint8Array.from(atob(data), (c) => c.charCodeAt(0));
+  } catch {
+    bytes = new Uint8Array();
+  }
+
+  // On failure the game displays an explanation, so discard
+  // the unusable save and carry on with a fresh game
+  if (!game.load(bytes)) {
+    localStorage.removeItem(SAVE_KEY);
+  }
+  return true;
+}
+
+// Offers the recording of a finished game for download
+function offerReplay() {
+  const link = document.getElementById("game-replay");
+  const blob = new Blob([game.export_replay()],
+                        {type: "application/octet-stream"});
+  link.href = URL.createObjectURL(blob);
+  link.download = "star_trip-" + game.seed() + ".replay";
+  link.hidden = false;
+}
+
+// Handles replay files dropped onto the canvas
+async function handleDrop(e) {
+  e.preventDefault();
+  const file = e.dataTransfer.files[0];
+  if (drawing || !file) {
+    return;
+  }
+
+  const bytes = new Uint8Array(await file.arrayBuffer());
+  game.import_replay(bytes);
+  input.focus({preventScroll: true});
+
+  if (!started) {
+    started = true;
+    input.addEventListener("keydown", handleKeys);
+  }
+  await drawScreen(true);
+}
+
 // Handles mouse clicks
 async function handleClicks() {
   input.focus({preventScroll: true});
 
   if(!started) {
-    // Start on first click
+    // Start on first click, resuming any saved game
     started = true;
-    game.intro();
+    if (!restoreGame()) {
+      game.intro();
+    }
     await drawScreen(true);
 
     // Start listening to keyboard input